@@ -0,0 +1,150 @@
+//! Message-to-readable latency of `WsData`: the time from handing a decoded websocket
+//! message to `WsData` until a bot can read it back.
+//!
+//! Run with `cargo bench --bench ws_latency`.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rust_trading::hyperliquid::model::{CustomCandle, CustomL2Book, CustomLevel, CustomTrade};
+use rust_trading::hyperliquid::websocket::WsData;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+
+fn l2_book(mid: f64, depth: usize) -> CustomL2Book {
+    let level = |price: f64| CustomLevel {
+        price,
+        size: 1.0,
+        num_orders: 1,
+    };
+    CustomL2Book {
+        coin: "BTC".to_string(),
+        bid_levels: (1..=depth).map(|i| level(mid - i as f64)).collect(),
+        ask_levels: (1..=depth).map(|i| level(mid + i as f64)).collect(),
+        timestamp: 0,
+    }
+}
+
+fn trade(timestamp: u64) -> CustomTrade {
+    CustomTrade {
+        coin: "BTC".to_string(),
+        side: "B".to_string(),
+        price: 100.0,
+        size: 1.0,
+        timestamp,
+        hash: "0x0".to_string(),
+    }
+}
+
+fn candle(time_open: u64) -> CustomCandle {
+    CustomCandle {
+        coin: "BTC".to_string(),
+        interval: "1m".to_string(),
+        open: 100.0,
+        high: 101.0,
+        low: 99.0,
+        close: 100.5,
+        volume: 10.0,
+        num_trades: 5,
+        time_close: time_open + 59_999,
+        time_open,
+    }
+}
+
+fn bench_l2_book(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let ws_data = runtime.block_on(async { WsData::new(None) });
+    let book = l2_book(100_000.0, 20);
+
+    c.bench_function("l2_book_to_best_bid", |b| {
+        b.to_async(&runtime).iter(|| async {
+            ws_data.add_l2_book(book.clone()).await;
+            ws_data.best_bid()
+        })
+    });
+}
+
+fn bench_trades(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let mut group = c.benchmark_group("trades_to_cursor_read");
+    // A full buffer is the steady state, where trimming used to cost O(n)
+    for capacity in [1_000usize, 10_000] {
+        let ws_data = runtime.block_on(async {
+            let ws_data = WsData::new(None);
+            ws_data.trades.write().await.set_capacity(capacity);
+            ws_data
+                .add_trade((0..capacity as u64).map(trade).collect())
+                .await;
+            ws_data
+        });
+        group.bench_with_input(BenchmarkId::from_parameter(capacity), &capacity, |b, _| {
+            b.to_async(&runtime).iter(|| async {
+                let cursor = ws_data.trades.read().await.last_seq();
+                ws_data.add_trade(vec![trade(0)]).await;
+                ws_data.trades.read().await.since(cursor)
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_candle_update(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let ws_data = runtime.block_on(async {
+        let ws_data = WsData::new(None);
+        for i in 0..10_000 {
+            ws_data.add_candle(candle(i * 60_000)).await;
+        }
+        ws_data
+    });
+
+    c.bench_function("candle_update_to_read", |b| {
+        b.to_async(&runtime).iter(|| async {
+            ws_data.add_candle(candle(9_999 * 60_000)).await;
+            ws_data
+                .candles
+                .read()
+                .await
+                .last()
+                .map(|candle| candle.close)
+        })
+    });
+}
+
+// Book reads must not wait on the trade stream
+fn bench_best_bid_under_trade_flood(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let ws_data = Arc::new(runtime.block_on(async { WsData::new(None) }));
+    let running = Arc::new(AtomicBool::new(true));
+
+    let flood = {
+        let ws_data = ws_data.clone();
+        let running = running.clone();
+        runtime.spawn(async move {
+            let batch: Vec<CustomTrade> = (0..100).map(trade).collect();
+            while running.load(Ordering::Relaxed) {
+                ws_data.add_trade(batch.clone()).await;
+                tokio::task::yield_now().await;
+            }
+        })
+    };
+
+    let book = l2_book(100_000.0, 20);
+    c.bench_function("l2_book_to_best_bid_under_trade_flood", |b| {
+        b.to_async(&runtime).iter(|| async {
+            ws_data.add_l2_book(book.clone()).await;
+            ws_data.best_bid()
+        })
+    });
+
+    running.store(false, Ordering::Relaxed);
+    runtime.block_on(flood).unwrap();
+}
+
+criterion_group!(
+    benches,
+    bench_l2_book,
+    bench_trades,
+    bench_candle_update,
+    bench_best_bid_under_trade_flood
+);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+use dotenv::dotenv;
+use ethers::signers::{LocalWallet, Signer};
+use log::{error, info};
+use rust_trading::hyperliquid::http::HttpClient;
+use std::env;
+use std::str::FromStr;
+
+#[tokio::main]
+async fn main() {
+    // Initialize the logger with environment variables
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    // Load environment variables from a .env file
+    dotenv().ok();
+
+    // The main wallet is only needed once, to approve the agent
+    let private_key = env::var("WALLET_SECRET").expect("WALLET_SECRET not set");
+    let wallet = LocalWallet::from_str(&private_key).expect("Invalid private key");
+    let account_address = wallet.address();
+
+    let client = HttpClient::new(true, wallet, None).await.unwrap();
+
+    match client.approve_agent().await {
+        Ok(agent_secret) => {
+            let agent = LocalWallet::from_str(&agent_secret).expect("Invalid agent key");
+            info!(
+                "Approved agent {:?} for {:?}",
+                agent.address(),
+                account_address
+            );
+            // Put these into the bot config as wallet_secret / account_address
+            println!("wallet_secret = \"{}\"", agent_secret);
+            println!("account_address = \"{:?}\"", account_address);
+        }
+        Err(err) => {
+            error!("Failed to approve agent: {}", err);
+        }
+    }
+}
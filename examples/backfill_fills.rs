@@ -0,0 +1,42 @@
+use chrono::{Duration, Utc};
+use dotenv::dotenv;
+use ethers::signers::{LocalWallet, Signer};
+use log::info;
+use rust_trading::hyperliquid::backfill::backfill_user_fills;
+use rust_trading::hyperliquid::db::connect_storage;
+use rust_trading::hyperliquid::http::HttpClient;
+use std::env;
+use std::str::FromStr;
+
+// Run periodically (e.g. from cron) to close the gaps left while a bot was offline
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    dotenv().ok();
+
+    let private_key = env::var("WALLET_SECRET").expect("WALLET_SECRET not set");
+    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL not set");
+    let wallet = LocalWallet::from_str(&private_key).expect("Invalid private key");
+    let user = wallet.address();
+
+    let client = HttpClient::new(true, wallet, None).await?;
+    let storage = connect_storage(&database_url).await?;
+    storage.migrate().await?;
+
+    // Last 30 days, treating an hour without fills as a possible gap
+    let end_time = Utc::now().timestamp_millis();
+    let start_time = end_time - Duration::days(30).num_milliseconds();
+    let min_gap_ms = Duration::hours(1).num_milliseconds();
+
+    let count = backfill_user_fills(
+        &client,
+        storage.as_ref(),
+        user,
+        start_time,
+        end_time,
+        min_gap_ms,
+    )
+    .await?;
+    info!("Backfilled {} fills", count);
+    Ok(())
+}
@@ -22,7 +22,7 @@ async fn main() {
     let address = wallet.address();
 
     // Initialize the Hyperliquid HTTP client in mainnet mode
-    let client = HttpClient::new(true, wallet).await.unwrap();
+    let client = HttpClient::new(true, wallet, None).await.unwrap();
 
     // Fetch and display open orders for the wallet address
     match client.fetch_open_orders(address).await {
@@ -140,6 +140,17 @@ async fn main() {
     if let Some(asset_info) = client.get_asset_info("HYPE/USDC") {
         info!("Asset info: {:#?}", asset_info);
     }
+    // Full metadata by display name, internal name ("@107") or asset index
+    match client.resolve_asset("@107") {
+        Ok(asset) => info!(
+            "{}: max leverage {:?}, tick size {} at 25.0, base token {:?}",
+            asset.name,
+            asset.max_leverage,
+            asset.tick_size(25.0),
+            asset.base_token
+        ),
+        Err(err) => error!("{}", err),
+    }
 
     // Fetch the user's state and log the result or an error message
     match client.fetch_user_state(address).await {
@@ -23,7 +23,7 @@ async fn main() {
     info!("Wallet address: {:?}", wallet.address());
 
     // Initialize the Hyperliquid HTTP client in mainnet mode
-    let client = HttpClient::new(true, wallet).await.unwrap();
+    let client = HttpClient::new(true, wallet, None).await.unwrap();
 
     // Define a limit order with the desired parameters
     let order = LimitOrderParams::new("HYPE/USDC".to_string(), true, 8.0, 0.5);
@@ -0,0 +1,54 @@
+use chrono::{FixedOffset, NaiveDate, TimeZone};
+use ethers::types::H160;
+use rust_trading::hyperliquid::db::connect_storage;
+use rust_trading::hyperliquid::fill_log::{
+    export_fills, fill_log_path, import_fills, load_fills_from_csv, load_fills_from_log,
+};
+use rust_trading::hyperliquid::portfolio::PortfolioManager;
+use std::error::Error;
+use std::path::Path;
+use std::str::FromStr;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    let user = H160::from_str("your public key").unwrap();
+    let jst = FixedOffset::east_opt(9 * 3600).unwrap(); // JST (+09:00)
+
+    // Fills written by a bot running without a database, plus a trade history CSV from the UI
+    let mut fills = load_fills_from_log(&fill_log_path(user))?;
+    fills.extend(load_fills_from_csv(Path::new("trade_history.csv"), jst)?);
+    println!("Loaded {} fills from files", fills.len());
+
+    // Replay offline
+    let portfolio = PortfolioManager::from_fills(&fills);
+    println!("Realized PnL: {}", portfolio.get_total_realized_pnl());
+    portfolio.create_pnl_chart(&fills, "pnl_chart.png")?;
+
+    // Import into the database and export one day back out
+    let storage = connect_storage("sqlite://fills.db").await?;
+    storage.migrate().await?;
+    let imported = import_fills(storage.as_ref(), user, &fills).await?;
+    println!("Imported {} fills", imported);
+
+    let date = NaiveDate::from_ymd_opt(2024, 12, 27).unwrap();
+    let start_time = jst
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .unwrap()
+        .timestamp_millis();
+    let end_time = jst
+        .from_local_datetime(&date.and_hms_milli_opt(23, 59, 59, 999).unwrap())
+        .unwrap()
+        .timestamp_millis();
+    for path in ["fills.csv", "fills.parquet"] {
+        let count = export_fills(
+            storage.as_ref(),
+            user,
+            start_time,
+            end_time,
+            Path::new(path),
+        )
+        .await?;
+        println!("Exported {} fills to {}", count, path);
+    }
+    Ok(())
+}
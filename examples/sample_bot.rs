@@ -1,16 +1,23 @@
 use anyhow::Result;
-use ethers::signers::Signer;
 use log::info;
+use rust_trading::bot_framework::config::Config;
 use rust_trading::bot_framework::framework::{run_bot, BotFramework};
 use rust_trading::bot_framework::init::InitResources;
 use rust_trading::hyperliquid::subscriptions::Subscription;
-use serde_json::Value;
+use serde::Deserialize;
+
+/// Settings read from the [bot_specific] section of the config
+#[derive(Debug, Deserialize)]
+pub struct SampleBotSettings {
+    #[serde(default)]
+    pub threshold: f64,
+}
 
 pub struct SampleBot;
 
 #[async_trait::async_trait]
-impl BotFramework for SampleBot {
-    async fn subscribe(&mut self, resources: &InitResources) -> Result<()> {
+impl BotFramework<SampleBotSettings> for SampleBot {
+    async fn subscribe(&mut self, resources: &InitResources<SampleBotSettings>) -> Result<()> {
         let ws_manager = &resources.ws_manager;
         let config = &resources.config;
         ws_manager
@@ -20,23 +27,18 @@ impl BotFramework for SampleBot {
             .await?;
         ws_manager
             .subscribe(Subscription::UserFills {
-                user: resources.wallet.address(),
+                user: resources.account_address,
             })
             .await?;
         info!("Subscribed to L2Book for {}", config.coin);
         Ok(())
     }
 
-    async fn execute(&mut self, resources: &InitResources) -> Result<()> {
+    async fn execute(&mut self, resources: &InitResources<SampleBotSettings>) -> Result<()> {
         let config = &resources.config;
 
-        // Example of loading bot-specific settings
-        let threshold: f64 = config
-            .bot_specific
-            .get("threshold")
-            .and_then(Value::as_f64)
-            .unwrap_or(0.0);
-        info!("custom bot config: {}", threshold);
+        // Example of using bot-specific settings
+        info!("custom bot config: {}", resources.settings.threshold);
 
         let ws_manager = &resources.ws_manager;
         let best_bid = ws_manager.get_best_bid().await;
@@ -62,11 +64,23 @@ impl BotFramework for SampleBot {
 
         Ok(())
     }
+
+    async fn on_config_change(
+        &mut self,
+        resources: &InitResources<SampleBotSettings>,
+        previous: &Config,
+    ) -> Result<()> {
+        info!(
+            "Config reloaded, interval: {} -> {}, threshold: {}",
+            previous.interval, resources.config.interval, resources.settings.threshold
+        );
+        Ok(())
+    }
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let config_path = "examples/config/sample_bot.toml";
     let bot = SampleBot;
-    run_bot(bot, config_path).await
+    run_bot::<_, SampleBotSettings>(bot, config_path).await
 }
@@ -26,7 +26,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     // Initialize the WebSocketManager with the mainnet URL
     let ws_manager = WebSocketManager::new(true, None).await;
-    let http_manager = HttpClient::new(true, wallet).await.unwrap();
+    let http_manager = HttpClient::new(true, wallet, None).await.unwrap();
     // Set the maximum number of trades to store
     ws_manager.set_max_trades(200).await;
 
@@ -1,4 +1,4 @@
-CREATE TABLE user_fills (
+CREATE TABLE IF NOT EXISTS user_fills (
     id SERIAL PRIMARY KEY,
     user_address VARCHAR(100) NOT NULL,
     closed_pnl DOUBLE PRECISION NOT NULL,
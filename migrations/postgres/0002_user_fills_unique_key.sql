@@ -0,0 +1,25 @@
+-- Fills are upserted on (user_address, hash, order_id, timestamp, price, size, start_position)
+-- so replayed fills are not duplicated. tid is stored but kept out of the key: the userFills
+-- response and fill logs written before tid was recorded carry none, so a key on tid would
+-- never match the same fill stored from the websocket, and every replay would insert it again.
+ALTER TABLE user_fills ADD COLUMN IF NOT EXISTS tid BIGINT NOT NULL DEFAULT 0;
+
+-- An earlier version of this file applied by hand keyed on tid with placeholder tids
+ALTER TABLE user_fills DROP CONSTRAINT IF EXISTS user_fills_unique_fill;
+UPDATE user_fills SET tid = 0 WHERE tid < 0;
+
+-- Drop copies left by replays of the same fill, keeping one with a tid
+DELETE FROM user_fills a
+USING user_fills b
+WHERE a.user_address = b.user_address
+  AND a.hash = b.hash
+  AND a.order_id = b.order_id
+  AND a.timestamp = b.timestamp
+  AND a.price = b.price
+  AND a.size = b.size
+  AND a.start_position = b.start_position
+  AND (b.tid > a.tid OR (b.tid = a.tid AND b.id < a.id));
+
+ALTER TABLE user_fills
+    ADD CONSTRAINT user_fills_natural_key
+    UNIQUE (user_address, hash, order_id, timestamp, price, size, start_position);
@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS orders (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    coin VARCHAR(50) NOT NULL,
+    order_id BIGINT,
+    cloid VARCHAR(64),
+    side VARCHAR(10) NOT NULL,
+    price DOUBLE PRECISION,
+    size DOUBLE PRECISION NOT NULL,
+    order_type VARCHAR(20) NOT NULL,
+    reduce_only BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(30) NOT NULL,
+    created_at BIGINT NOT NULL,
+    updated_at BIGINT NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS orders_user_address_order_id_idx ON orders (user_address, order_id);
+CREATE INDEX IF NOT EXISTS orders_cloid_idx ON orders (cloid);
+CREATE INDEX IF NOT EXISTS orders_user_address_created_at_idx ON orders (user_address, created_at);
+
+CREATE TABLE IF NOT EXISTS order_status_transitions (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    order_id BIGINT,
+    cloid VARCHAR(64),
+    status VARCHAR(30) NOT NULL,
+    message TEXT,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_status_transitions_order_idx ON order_status_transitions (user_address, order_id);
+CREATE INDEX IF NOT EXISTS order_status_transitions_cloid_idx ON order_status_transitions (cloid);
+CREATE INDEX IF NOT EXISTS order_status_transitions_timestamp_idx ON order_status_transitions (timestamp);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS funding_payments (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    coin VARCHAR(50) NOT NULL,
+    usdc DOUBLE PRECISION NOT NULL,
+    position_size DOUBLE PRECISION NOT NULL,
+    funding_rate DOUBLE PRECISION NOT NULL,
+    hash VARCHAR(100) NOT NULL,
+    timestamp BIGINT NOT NULL,
+    UNIQUE (user_address, coin, timestamp)
+);
+
+CREATE INDEX IF NOT EXISTS funding_payments_timestamp_idx ON funding_payments (timestamp);
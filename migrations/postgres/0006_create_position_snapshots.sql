@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS position_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    coin VARCHAR(50) NOT NULL,
+    amount DOUBLE PRECISION NOT NULL,
+    average_price DOUBLE PRECISION NOT NULL,
+    realized_pnl DOUBLE PRECISION NOT NULL,
+    unrealized_pnl DOUBLE PRECISION NOT NULL,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS position_snapshots_user_address_timestamp_idx ON position_snapshots (user_address, timestamp);
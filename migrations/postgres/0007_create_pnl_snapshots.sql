@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS pnl_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    realized_pnl DOUBLE PRECISION NOT NULL,
+    unrealized_pnl DOUBLE PRECISION NOT NULL,
+    fees DOUBLE PRECISION NOT NULL,
+    account_value DOUBLE PRECISION,
+    timestamp BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS pnl_snapshots_user_address_timestamp_idx ON pnl_snapshots (user_address, timestamp);
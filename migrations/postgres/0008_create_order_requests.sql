@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS order_requests (
+    id BIGSERIAL PRIMARY KEY,
+    user_address VARCHAR(100) NOT NULL,
+    action VARCHAR(30) NOT NULL,
+    coin VARCHAR(50) NOT NULL,
+    order_id BIGINT,
+    cloid VARCHAR(64),
+    side VARCHAR(10),
+    price DOUBLE PRECISION,
+    size DOUBLE PRECISION,
+    order_type VARCHAR(20),
+    reduce_only BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(30) NOT NULL,
+    response TEXT NOT NULL,
+    sent_at BIGINT NOT NULL,
+    latency_ms BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_requests_user_address_sent_at_idx ON order_requests (user_address, sent_at);
+CREATE INDEX IF NOT EXISTS order_requests_cloid_idx ON order_requests (cloid);
@@ -0,0 +1,16 @@
+CREATE TABLE IF NOT EXISTS user_fills (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    closed_pnl REAL NOT NULL,
+    coin TEXT NOT NULL,
+    crossed INTEGER NOT NULL,
+    dir TEXT NOT NULL,
+    hash TEXT NOT NULL,
+    order_id INTEGER NOT NULL,
+    price REAL NOT NULL,
+    side TEXT NOT NULL,
+    start_position REAL NOT NULL,
+    size REAL NOT NULL,
+    timestamp INTEGER NOT NULL,
+    fee REAL NOT NULL
+);
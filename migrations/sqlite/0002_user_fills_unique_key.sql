@@ -0,0 +1,7 @@
+-- Fills are upserted on (user_address, hash, order_id, timestamp, price, size, start_position)
+-- so replayed fills are not duplicated. tid is stored but kept out of the key, since fills from
+-- the userFills response and older fill logs carry none.
+ALTER TABLE user_fills ADD COLUMN tid INTEGER NOT NULL DEFAULT 0;
+
+CREATE UNIQUE INDEX IF NOT EXISTS user_fills_natural_key
+    ON user_fills (user_address, hash, order_id, timestamp, price, size, start_position);
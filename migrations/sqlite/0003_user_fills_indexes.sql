@@ -0,0 +1,2 @@
+CREATE INDEX IF NOT EXISTS user_fills_user_address_timestamp_idx ON user_fills (user_address, timestamp);
+CREATE INDEX IF NOT EXISTS user_fills_timestamp_idx ON user_fills (timestamp);
@@ -0,0 +1,33 @@
+CREATE TABLE IF NOT EXISTS orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    order_id INTEGER,
+    cloid TEXT,
+    side TEXT NOT NULL,
+    price REAL,
+    size REAL NOT NULL,
+    order_type TEXT NOT NULL,
+    reduce_only INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS orders_user_address_order_id_idx ON orders (user_address, order_id);
+CREATE INDEX IF NOT EXISTS orders_cloid_idx ON orders (cloid);
+CREATE INDEX IF NOT EXISTS orders_user_address_created_at_idx ON orders (user_address, created_at);
+
+CREATE TABLE IF NOT EXISTS order_status_transitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    order_id INTEGER,
+    cloid TEXT,
+    status TEXT NOT NULL,
+    message TEXT,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_status_transitions_order_idx ON order_status_transitions (user_address, order_id);
+CREATE INDEX IF NOT EXISTS order_status_transitions_cloid_idx ON order_status_transitions (cloid);
+CREATE INDEX IF NOT EXISTS order_status_transitions_timestamp_idx ON order_status_transitions (timestamp);
@@ -0,0 +1,13 @@
+CREATE TABLE IF NOT EXISTS funding_payments (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    usdc REAL NOT NULL,
+    position_size REAL NOT NULL,
+    funding_rate REAL NOT NULL,
+    hash TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    UNIQUE (user_address, coin, timestamp)
+);
+
+CREATE INDEX IF NOT EXISTS funding_payments_timestamp_idx ON funding_payments (timestamp);
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS position_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    amount REAL NOT NULL,
+    average_price REAL NOT NULL,
+    realized_pnl REAL NOT NULL,
+    unrealized_pnl REAL NOT NULL,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS position_snapshots_user_address_timestamp_idx ON position_snapshots (user_address, timestamp);
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS pnl_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    realized_pnl REAL NOT NULL,
+    unrealized_pnl REAL NOT NULL,
+    fees REAL NOT NULL,
+    account_value REAL,
+    timestamp INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS pnl_snapshots_user_address_timestamp_idx ON pnl_snapshots (user_address, timestamp);
@@ -0,0 +1,20 @@
+CREATE TABLE IF NOT EXISTS order_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_address TEXT NOT NULL,
+    action TEXT NOT NULL,
+    coin TEXT NOT NULL,
+    order_id INTEGER,
+    cloid TEXT,
+    side TEXT,
+    price REAL,
+    size REAL,
+    order_type TEXT,
+    reduce_only INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL,
+    response TEXT NOT NULL,
+    sent_at INTEGER NOT NULL,
+    latency_ms INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS order_requests_user_address_sent_at_idx ON order_requests (user_address, sent_at);
+CREATE INDEX IF NOT EXISTS order_requests_cloid_idx ON order_requests (cloid);
@@ -0,0 +1,255 @@
+use crate::hyperliquid::model::CustomUserFills;
+use crate::hyperliquid::websocket::WebSocketManager;
+use crate::utils::notifier::{alert, Alert, Severity};
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashSet;
+
+/// `[alerts]` section of the bot config: events the framework reports through the
+/// notifiers on its own. Each event has an enable flag, thresholds are in quote currency.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlertsConfig {
+    #[serde(default = "default_true")]
+    pub start_stop: bool,
+    #[serde(default = "default_true")]
+    pub ws_disconnect: bool,
+    #[serde(default = "default_true")]
+    pub execute_failures: bool,
+    #[serde(default = "default_execute_failure_threshold")]
+    pub execute_failure_threshold: u32, // Consecutive failures before alerting
+    #[serde(default)]
+    pub large_fills: bool,
+    pub large_fill_notional: Option<f64>, // price * size
+    #[serde(default)]
+    pub position_limit: bool,
+    pub max_position: Option<f64>, // Absolute position size of any coin
+    #[serde(default)]
+    pub drawdown: bool,
+    #[serde(default)]
+    pub drawdown_thresholds: Vec<f64>, // Loss from the peak of realized + unrealized PnL
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_execute_failure_threshold() -> u32 {
+    3
+}
+
+impl AlertsConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.execute_failures && self.execute_failure_threshold == 0 {
+            errors.push("alerts.execute_failure_threshold: must be greater than 0".to_string());
+        }
+        if self.large_fills && self.large_fill_notional.is_none_or(|value| value <= 0.0) {
+            errors.push(
+                "alerts.large_fill_notional: required and positive when large_fills is set"
+                    .to_string(),
+            );
+        }
+        if self.position_limit && self.max_position.is_none_or(|value| value <= 0.0) {
+            errors.push(
+                "alerts.max_position: required and positive when position_limit is set".to_string(),
+            );
+        }
+        if self.drawdown
+            && (self.drawdown_thresholds.is_empty()
+                || self.drawdown_thresholds.iter().any(|value| *value <= 0.0))
+        {
+            errors.push(
+                "alerts.drawdown_thresholds: required and positive when drawdown is set"
+                    .to_string(),
+            );
+        }
+        errors
+    }
+}
+
+/// Turns run loop and websocket events into alerts. Every method is a no-op
+/// while the config has no `[alerts]` section or the event is disabled.
+#[derive(Default)]
+pub struct AlertMonitor {
+    consecutive_failures: u32,
+    disconnected: bool,
+    over_limit: HashSet<String>,
+    peak_pnl: Option<f64>,
+    drawdown_level: usize, // Number of thresholds crossed since the last peak
+    missing_mids: HashSet<String>, // Held coins already logged as having no mid price
+}
+
+impl AlertMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn started(&self, config: Option<&AlertsConfig>, coin: &str) {
+        if config.is_some_and(|config| config.start_stop) {
+            alert(Alert::new(Severity::Info, "lifecycle", "Bot started").message(coin)).await;
+        }
+    }
+
+    pub async fn stopped(&self, config: Option<&AlertsConfig>, reason: &str) {
+        if config.is_some_and(|config| config.start_stop) {
+            alert(Alert::new(Severity::Warning, "lifecycle", "Bot stopped").message(reason)).await;
+        }
+    }
+
+    pub async fn execute_succeeded(&mut self, config: Option<&AlertsConfig>) {
+        let failures = std::mem::take(&mut self.consecutive_failures);
+        let Some(config) = config.filter(|config| config.execute_failures) else {
+            return;
+        };
+        if failures >= config.execute_failure_threshold {
+            let message = format!("Recovered after {} failures", failures);
+            alert(Alert::new(Severity::Info, "error", "Execute recovered").message(&message)).await;
+        }
+    }
+
+    /// Alerts once when the failures reach the threshold, not on every one after it
+    pub async fn execute_failed(&mut self, config: Option<&AlertsConfig>, error: &str) {
+        self.consecutive_failures += 1;
+        let Some(config) = config.filter(|config| config.execute_failures) else {
+            return;
+        };
+        if self.consecutive_failures == config.execute_failure_threshold {
+            let title = format!(
+                "Execute failed {} times in a row",
+                self.consecutive_failures
+            );
+            alert(Alert::new(Severity::Error, "error", &title).message(error)).await;
+        }
+    }
+
+    /// Call before `check_health`, which clears the disconnect
+    pub async fn check_connection(
+        &mut self,
+        config: Option<&AlertsConfig>,
+        ws_manager: &WebSocketManager,
+    ) {
+        let disconnected = !ws_manager.is_connected().await;
+        let changed = std::mem::replace(&mut self.disconnected, disconnected) != disconnected;
+        if !changed || !config.is_some_and(|config| config.ws_disconnect) {
+            return;
+        }
+        if disconnected {
+            alert(Alert::new(Severity::Error, "ws", "Websocket disconnected")).await;
+        } else {
+            alert(Alert::new(Severity::Info, "ws", "Websocket reconnected")).await;
+        }
+    }
+
+    pub async fn on_fill(
+        &mut self,
+        config: Option<&AlertsConfig>,
+        ws_manager: &WebSocketManager,
+        fill: &CustomUserFills,
+    ) {
+        let Some(config) = config else {
+            return;
+        };
+
+        let notional = fill.price * fill.size;
+        if config.large_fills
+            && config
+                .large_fill_notional
+                .is_some_and(|limit| notional >= limit)
+        {
+            let side = if fill.side == "B" { "Buy" } else { "Sell" };
+            let message = format!(
+                "{} {} {} @ {} ({:.2} notional, oid {})",
+                side, fill.size, fill.coin, fill.price, notional, fill.order_id
+            );
+            alert(Alert::new(Severity::Info, "fill", "Large fill").message(&message)).await;
+        }
+
+        let Some(max_position) = config.max_position.filter(|_| config.position_limit) else {
+            return;
+        };
+        let amount = ws_manager
+            .get_position(&fill.coin)
+            .await
+            .map_or(0.0, |position| position.amount);
+        if amount.abs() < max_position {
+            self.over_limit.remove(&fill.coin);
+        } else if self.over_limit.insert(fill.coin.clone()) {
+            let message = format!(
+                "{} position {} reached the limit of {}",
+                fill.coin, amount, max_position
+            );
+            alert(Alert::new(Severity::Warning, "risk", "Position limit hit").message(&message))
+                .await;
+        }
+    }
+
+    /// Tracks realized + unrealized PnL of every position against its peak
+    pub async fn check_drawdown(
+        &mut self,
+        config: Option<&AlertsConfig>,
+        ws_manager: &WebSocketManager,
+    ) {
+        let Some(config) = config.filter(|config| config.drawdown) else {
+            return;
+        };
+
+        let all_mids = ws_manager.get_all_mids().await;
+        let mut pnl = 0.0;
+        for (coin, position) in ws_manager.get_positions().await {
+            pnl += position.pnl.realized;
+            if position.amount == 0.0 {
+                continue;
+            }
+            // Valued at the entry price until a mid arrives, so other coins are still tracked
+            match all_mids
+                .get(&coin)
+                .and_then(|price| price.parse::<f64>().ok())
+            {
+                Some(price) => {
+                    self.missing_mids.remove(&coin);
+                    pnl += (price - position.average_price) * position.amount;
+                }
+                None => {
+                    if self.missing_mids.insert(coin.clone()) {
+                        warn!(
+                            "No mid price for {}, drawdown excludes its unrealized PnL",
+                            coin
+                        );
+                    }
+                }
+            }
+        }
+
+        let peak = self.peak_pnl.get_or_insert(pnl);
+        if pnl >= *peak {
+            *peak = pnl;
+            self.drawdown_level = 0;
+            return;
+        }
+        let drawdown = *peak - pnl;
+
+        let mut thresholds = config.drawdown_thresholds.clone();
+        thresholds.sort_by(|a, b| a.total_cmp(b));
+        let level = thresholds
+            .iter()
+            .take_while(|threshold| drawdown >= **threshold)
+            .count();
+        if level > self.drawdown_level {
+            self.drawdown_level = level;
+            let message = format!(
+                "Down {:.2} from the peak PnL of {:.2} (threshold {})",
+                drawdown,
+                *peak,
+                thresholds[level - 1]
+            );
+            let severity = if level == thresholds.len() {
+                Severity::Critical
+            } else {
+                Severity::Warning
+            };
+            alert(Alert::new(severity, "risk", "Drawdown threshold crossed").message(&message))
+                .await;
+        }
+    }
+}
@@ -0,0 +1,390 @@
+use crate::bot_framework::alerts::AlertsConfig;
+use crate::bot_framework::control::ControlConfig;
+use crate::bot_framework::secret::WalletSecret;
+use crate::bot_framework::summary::SummaryConfig;
+use crate::hyperliquid::rate_limit::RateLimitConfig;
+use crate::hyperliquid::retry::RetryConfig;
+use crate::utils::logger::LoggingConfig;
+use crate::utils::metrics::MetricsConfig;
+use crate::utils::notifier::NotifierConfig;
+use anyhow::{bail, Context, Result};
+use ethers::types::H160;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Selects the overlay file, e.g. `BOT_ENV=prod` loads `sample_bot.prod.toml` on top of `sample_bot.toml`
+const ENV_NAME_VAR: &str = "BOT_ENV";
+/// Prefix of env var overrides, e.g. `BOT__INTERVAL=10` or `BOT__BOT_SPECIFIC__THRESHOLD=0.02`
+const ENV_OVERRIDE_PREFIX: &str = "BOT__";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    #[serde(default)] // Read from WALLET_SECRET if wallet_secret is missing
+    pub wallet_secret: WalletSecret,
+    pub is_mainnet: bool,
+    pub coin: String,
+    pub interval: u64,
+    pub database_url: Option<String>,
+    pub vault_address: Option<H160>, // (Optional) Vault or sub-account to trade on behalf of
+    pub account_address: Option<H160>, // (Optional) Main account when wallet_secret is an agent key
+    pub control: Option<ControlConfig>, // (Optional) Local control server
+    pub metrics: Option<MetricsConfig>, // (Optional) Prometheus /metrics endpoint
+    pub summary: Option<SummaryConfig>, // (Optional) Scheduled PnL summaries to Discord
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>, // Alert sinks ([[notifiers]]), alerts are only logged if empty
+    pub alerts: Option<AlertsConfig>, // (Optional) Alerts the framework sends on its own
+    pub logging: Option<LoggingConfig>, // (Optional) Log format, levels, files and the audit log
+    pub rate_limit: Option<RateLimitConfig>, // (Optional) Request weight budget of HttpClient
+    pub retry: Option<RetryConfig>,   // (Optional) Timeouts and retries of HttpClient calls
+    #[serde(default)] // User empty object if bot_specific is missing
+    pub bot_specific: Value, // Bot-specific configuration
+}
+
+impl Config {
+    /// Loads the base file, the `BOT_ENV` overlay and `BOT__*` env overrides, in that order,
+    /// and fails with every unknown key and invalid field at once.
+    pub fn load(config_path: &str) -> Result<Self> {
+        let mut merged = read_table(Path::new(config_path))?;
+
+        if let Ok(env_name) = env::var(ENV_NAME_VAR) {
+            let overlay_path = overlay_path(Path::new(config_path), &env_name);
+            let overlay = read_table(&overlay_path).with_context(|| {
+                format!("Failed to load overlay for {}={}", ENV_NAME_VAR, env_name)
+            })?;
+            merge_tables(&mut merged, overlay);
+        }
+
+        apply_env_overrides(&mut merged, env::vars())?;
+
+        let mut errors = Vec::new();
+        let config: Config = serde_ignored::deserialize(toml::Value::Table(merged), |path| {
+            errors.push(format!("{}: unknown key", path));
+        })
+        .context("Failed to parse config file")?;
+
+        errors.extend(config.validate());
+        if !errors.is_empty() {
+            bail!("Invalid configuration:\n  - {}", errors.join("\n  - "));
+        }
+
+        Ok(config)
+    }
+
+    /// Files the config is loaded from: the base file and the `BOT_ENV` overlay, if any
+    pub fn source_paths(config_path: &str) -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(config_path)];
+        if let Ok(env_name) = env::var(ENV_NAME_VAR) {
+            paths.push(overlay_path(Path::new(config_path), &env_name));
+        }
+        paths
+    }
+
+    /// Returns a message per invalid field, empty if the config is valid
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        if self.coin.trim().is_empty() {
+            errors.push("coin: must not be empty".to_string());
+        }
+        if self.interval == 0 {
+            errors.push("interval: must be greater than 0".to_string());
+        }
+        if let Some(database_url) = &self.database_url {
+            if !database_url.starts_with("postgres://")
+                && !database_url.starts_with("postgresql://")
+                && !database_url.starts_with("sqlite:")
+            {
+                errors.push(
+                    "database_url: must start with postgres://, postgresql:// or sqlite:"
+                        .to_string(),
+                );
+            }
+        }
+        if self.vault_address.is_some() && self.account_address.is_some() {
+            errors.push(
+                "account_address: cannot be combined with vault_address, the vault is the account"
+                    .to_string(),
+            );
+        }
+        if let Some(control) = &self.control {
+            errors.extend(control.validate());
+        }
+        if let Some(summary) = &self.summary {
+            errors.extend(summary.validate());
+        }
+        if let Some(logging) = &self.logging {
+            errors.extend(logging.validate());
+        }
+        if let Some(alerts) = &self.alerts {
+            errors.extend(alerts.validate());
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            errors.extend(rate_limit.validate());
+        }
+        if let Some(retry) = &self.retry {
+            errors.extend(retry.validate());
+        }
+        for (index, notifier) in self.notifiers.iter().enumerate() {
+            errors.extend(notifier.validate(index));
+        }
+        if !self.bot_specific.is_null() && !self.bot_specific.is_object() {
+            errors.push("bot_specific: must be a table".to_string());
+        }
+
+        errors
+    }
+
+    /// Deserializes `bot_specific` into the bot's settings type, rejecting unknown keys
+    pub fn bot_settings<C: DeserializeOwned>(&self) -> Result<C> {
+        let value = if self.bot_specific.is_null() {
+            Value::Object(Default::default())
+        } else {
+            self.bot_specific.clone()
+        };
+
+        let mut unknown = Vec::new();
+        let settings = serde_ignored::deserialize(value, |path| {
+            unknown.push(format!("bot_specific.{}: unknown key", path));
+        })
+        .context("Failed to parse bot_specific settings")?;
+
+        if !unknown.is_empty() {
+            bail!("Invalid configuration:\n  - {}", unknown.join("\n  - "));
+        }
+        Ok(settings)
+    }
+}
+
+fn read_table(path: &Path) -> Result<toml::Table> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse config file {}", path.display()))
+}
+
+fn overlay_path(base: &Path, env_name: &str) -> PathBuf {
+    let stem = base
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("config");
+    base.with_file_name(format!("{}.{}.toml", stem, env_name))
+}
+
+// Tables are merged key by key, any other value in the overlay replaces the base value
+fn merge_tables(base: &mut toml::Table, overlay: toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table);
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn apply_env_overrides(
+    table: &mut toml::Table,
+    vars: impl Iterator<Item = (String, String)>,
+) -> Result<()> {
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_OVERRIDE_PREFIX) else {
+            continue;
+        };
+        let keys: Vec<String> = path.split("__").map(|key| key.to_lowercase()).collect();
+        if keys.iter().any(|key| key.is_empty()) {
+            bail!("Invalid config override {}", name);
+        }
+
+        let (last, parents) = keys.split_last().expect("split always yields a key");
+        let mut current = &mut *table;
+        for key in parents {
+            let entry = current
+                .entry(key.clone())
+                .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+            current = match entry {
+                toml::Value::Table(inner) => inner,
+                _ => bail!(
+                    "Config override {} conflicts with non-table key {}",
+                    name,
+                    key
+                ),
+            };
+        }
+        current.insert(last.clone(), parse_override(&raw));
+    }
+    Ok(())
+}
+
+// Numbers, booleans and inline arrays/tables keep their TOML type, anything else is a string
+fn parse_override(raw: &str) -> toml::Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", raw))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(content: &str) -> toml::Table {
+        toml::from_str(content).unwrap()
+    }
+
+    fn overrides(vars: &[(&str, &str)]) -> impl Iterator<Item = (String, String)> {
+        vars.iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    fn load(name: &str, content: &str) -> Result<Config> {
+        let path = env::temp_dir().join(format!("{}_{}.toml", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let config = Config::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        config
+    }
+
+    #[test]
+    fn merge_tables_merges_nested_tables() {
+        let mut base = table(
+            "interval = 5\n[bot_specific]\nthreshold = 0.01\nsize = 1.0\n[bot_specific.grid]\nlevels = 3",
+        );
+        merge_tables(
+            &mut base,
+            table("[bot_specific]\nthreshold = 0.02\n[bot_specific.grid]\nstep = 0.5"),
+        );
+        assert_eq!(
+            base,
+            table(
+                "interval = 5\n[bot_specific]\nthreshold = 0.02\nsize = 1.0\n[bot_specific.grid]\nlevels = 3\nstep = 0.5",
+            )
+        );
+    }
+
+    #[test]
+    fn merge_tables_replaces_arrays_and_scalars() {
+        let mut base = table("coins = [\"BTC\", \"ETH\"]\nlimits = { BTC = 1 }\ninterval = 5");
+        merge_tables(&mut base, table("coins = [\"SOL\"]\nlimits = 3"));
+        assert_eq!(base, table("coins = [\"SOL\"]\nlimits = 3\ninterval = 5"));
+    }
+
+    #[test]
+    fn parse_override_keeps_toml_types() {
+        assert_eq!(parse_override("true"), toml::Value::Boolean(true));
+        assert_eq!(parse_override("10"), toml::Value::Integer(10));
+        assert_eq!(parse_override("-0.25"), toml::Value::Float(-0.25));
+        assert_eq!(
+            parse_override("BTC"),
+            toml::Value::String("BTC".to_string())
+        );
+        assert_eq!(
+            parse_override("\"10\""),
+            toml::Value::String("10".to_string())
+        );
+        assert_eq!(
+            parse_override("[1, 2]"),
+            toml::Value::Array(vec![toml::Value::Integer(1), toml::Value::Integer(2)])
+        );
+        assert_eq!(
+            parse_override("postgres://user@localhost/db"),
+            toml::Value::String("postgres://user@localhost/db".to_string())
+        );
+    }
+
+    #[test]
+    fn env_overrides_set_nested_keys() {
+        let mut base = table("interval = 5\n[bot_specific]\nthreshold = 0.01");
+        apply_env_overrides(
+            &mut base,
+            overrides(&[
+                ("BOT__INTERVAL", "10"),
+                ("BOT__BOT_SPECIFIC__THRESHOLD", "0.02"),
+                ("BOT__CONTROL__LISTEN", "127.0.0.1:9100"),
+                ("HOME", "/root"),
+            ]),
+        )
+        .unwrap();
+        assert_eq!(
+            base,
+            table(
+                "interval = 10\n[bot_specific]\nthreshold = 0.02\n[control]\nlisten = \"127.0.0.1:9100\"",
+            )
+        );
+    }
+
+    #[test]
+    fn invalid_env_overrides_are_rejected() {
+        let mut base = table("interval = 5");
+        assert!(apply_env_overrides(&mut base, overrides(&[("BOT__", "1")])).is_err());
+        assert!(apply_env_overrides(&mut base, overrides(&[("BOT__A____B", "1")])).is_err());
+        assert!(
+            apply_env_overrides(&mut base, overrides(&[("BOT__INTERVAL__SECS", "1")])).is_err()
+        );
+    }
+
+    #[test]
+    fn load_reports_every_error_at_once() {
+        let error = load(
+            "config_rs_errors",
+            "is_mainnet = false\ncoin = \"\"\ninterval = 0\nintervall = 5\n[control]\nlisten = \"127.0.0.1:9100\"",
+        )
+        .unwrap_err()
+        .to_string();
+        for expected in [
+            "intervall: unknown key",
+            "coin: must not be empty",
+            "interval: must be greater than 0",
+            "control.token: required with listen",
+        ] {
+            assert!(
+                error.contains(expected),
+                "{} missing from {}",
+                expected,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn load_accepts_a_minimal_config() {
+        let config = load(
+            "config_rs_minimal",
+            "is_mainnet = false\ncoin = \"BTC\"\ninterval = 5\n[bot_specific]\nthreshold = 0.01",
+        )
+        .unwrap();
+        assert_eq!(config.coin, "BTC");
+        assert_eq!(config.wallet_secret, WalletSecret::default());
+        assert_eq!(config.bot_specific["threshold"], 0.01);
+    }
+
+    #[test]
+    fn bot_settings_rejects_unknown_keys() {
+        #[derive(Debug, Deserialize)]
+        struct Settings {
+            #[allow(dead_code)]
+            threshold: f64,
+        }
+        let mut config = load(
+            "config_rs_bot_settings",
+            "is_mainnet = false\ncoin = \"BTC\"\ninterval = 5\n[bot_specific]\nthreshold = 0.01",
+        )
+        .unwrap();
+        assert!(config.bot_settings::<Settings>().is_ok());
+
+        config.bot_specific["treshold"] = 0.02.into();
+        let error = config.bot_settings::<Settings>().unwrap_err().to_string();
+        assert!(
+            error.contains("bot_specific.treshold: unknown key"),
+            "{}",
+            error
+        );
+    }
+}
@@ -0,0 +1,607 @@
+use crate::bot_framework::init::InitResources;
+use crate::bot_framework::secret::TokenSecret;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{timeout, Duration};
+
+const MAX_BODY_SIZE: usize = 64 * 1024;
+const MAX_HEADER_COUNT: usize = 64;
+const MAX_LINE_LENGTH: usize = 8 * 1024; // Request line or a single header
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// `[control]` section of the bot config. Exactly one of `listen` (a loopback
+/// address such as "127.0.0.1:9100") or `unix_socket` has to be set.
+///
+/// Requests have to send `Authorization: Bearer <token>`. The token is required with
+/// `listen`, since any local process or web page can reach a loopback port, and
+/// optional with `unix_socket`, which only the owner can open.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ControlConfig {
+    pub listen: Option<SocketAddr>,
+    pub unix_socket: Option<PathBuf>,
+    pub token: Option<TokenSecret>,
+}
+
+impl ControlConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        match (&self.listen, &self.unix_socket) {
+            (Some(addr), None) if !addr.ip().is_loopback() => {
+                errors.push(format!(
+                    "control.listen: {} is not a loopback address",
+                    addr
+                ));
+            }
+            (Some(_), Some(_)) | (None, None) => {
+                errors.push("control: set exactly one of listen or unix_socket".to_string());
+            }
+            _ => {}
+        }
+        if self.listen.is_some() && self.token.is_none() {
+            errors.push("control.token: required with listen".to_string());
+        }
+        errors
+    }
+}
+
+/// Operations exposed by the control server, executed by the bot loop between ticks
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    Status,
+    Pause,
+    Resume,
+    CancelAll,
+    Flatten,
+    UpdateConfig(Value),
+}
+
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    pub respond_to: oneshot::Sender<Result<Value>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecuteError {
+    pub timestamp: i64,
+    pub message: String,
+}
+
+/// Binds the control server and returns the channel its requests arrive on.
+///
+/// Routes: `GET /status`, `POST /pause`, `POST /resume`, `POST /cancel_all`,
+/// `POST /flatten` and `POST /config` with a JSON body such as
+/// `{"interval": 10, "bot_specific": {"threshold": 0.02}}`.
+pub async fn spawn_control_server(
+    config: &ControlConfig,
+) -> Result<mpsc::Receiver<ControlRequest>> {
+    let (sender, receiver) = mpsc::channel(16);
+    let token = match &config.token {
+        Some(token) => Some(token.read().context("Failed to read control.token")?),
+        None => None,
+    };
+
+    if let Some(addr) = config.listen {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind control server to {}", addr))?;
+        info!("Control server listening on http://{}", addr);
+        let auth = Arc::new(ControlAuth {
+            token,
+            loopback_host: true,
+        });
+        tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(handle_connection(stream, sender.clone(), auth.clone()));
+                    }
+                    Err(e) => error!("Failed to accept control connection: {}", e),
+                }
+            }
+        });
+    } else if let Some(path) = &config.unix_socket {
+        let auth = Arc::new(ControlAuth {
+            token,
+            loopback_host: false,
+        });
+        spawn_unix_listener(path, sender, auth)?;
+    }
+
+    Ok(receiver)
+}
+
+#[cfg(unix)]
+fn spawn_unix_listener(
+    path: &Path,
+    sender: mpsc::Sender<ControlRequest>,
+    auth: Arc<ControlAuth>,
+) -> Result<()> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use tokio::net::UnixListener;
+
+    // A socket left over from a previous run would make bind fail, anything else at the
+    // path is most likely a misconfiguration and is left alone
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        Ok(_) => bail!(
+            "control.unix_socket: {} exists and is not a socket",
+            path.display()
+        ),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e).with_context(|| format!("Failed to stat {}", path.display()));
+        }
+    }
+    let listener = UnixListener::bind(path)
+        .with_context(|| format!("Failed to bind control socket {}", path.display()))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .context("Failed to restrict control socket permissions")?;
+    info!("Control server listening on {}", path.display());
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle_connection(stream, sender.clone(), auth.clone()));
+                }
+                Err(e) => error!("Failed to accept control connection: {}", e),
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn spawn_unix_listener(
+    _path: &Path,
+    _sender: mpsc::Sender<ControlRequest>,
+    _auth: Arc<ControlAuth>,
+) -> Result<()> {
+    bail!("control.unix_socket is only supported on unix")
+}
+
+struct ControlAuth {
+    token: Option<String>,
+    loopback_host: bool, // Reject non-loopback Host headers, against DNS rebinding
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>, // Lowercase names
+    body: Vec<u8>,
+}
+
+async fn handle_connection<S>(
+    stream: S,
+    sender: mpsc::Sender<ControlRequest>,
+    auth: Arc<ControlAuth>,
+) where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    let request = timeout(
+        Duration::from_secs(REQUEST_TIMEOUT_SECS),
+        read_request(&mut stream),
+    )
+    .await
+    .unwrap_or_else(|_| Err(anyhow!("Timed out reading the request")));
+    let (status, body) = match request {
+        Ok(request) => match authorize(&request, &auth)
+            .and_then(|()| route(&request.method, &request.path, &request.body))
+        {
+            Ok(command) => dispatch(command, &sender).await,
+            Err((status, message)) => (status, json!({ "error": message })),
+        },
+        Err(e) => (400, json!({ "error": e.to_string() })),
+    };
+
+    if let Err(e) = write_response(stream.get_mut(), status, &body).await {
+        warn!("Failed to write control response: {}", e);
+    }
+}
+
+async fn dispatch(command: ControlCommand, sender: &mpsc::Sender<ControlRequest>) -> (u16, Value) {
+    let (respond_to, response) = oneshot::channel();
+    if sender
+        .send(ControlRequest {
+            command,
+            respond_to,
+        })
+        .await
+        .is_err()
+    {
+        return (503, json!({ "error": "Bot loop is not running" }));
+    }
+
+    match response.await {
+        Ok(Ok(value)) => (200, value),
+        Ok(Err(e)) => (500, json!({ "error": format!("{:#}", e) })),
+        Err(_) => (503, json!({ "error": "Bot loop dropped the request" })),
+    }
+}
+
+// Browsers attach Origin to cross-site requests, including no-cors ones, and can't
+// set Authorization without a preflight the server never answers
+fn authorize(request: &HttpRequest, auth: &ControlAuth) -> Result<(), (u16, String)> {
+    if request.headers.contains_key("origin") {
+        return Err((403, "Requests from browsers are not allowed".to_string()));
+    }
+    if auth.loopback_host {
+        if let Some(host) = request.headers.get("host") {
+            if !is_loopback_host(host) {
+                return Err((403, format!("Host {} is not a loopback address", host)));
+            }
+        }
+    }
+    if let Some(token) = &auth.token {
+        let provided = request
+            .headers
+            .get("authorization")
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or("");
+        if !constant_time_eq(provided.trim().as_bytes(), token.as_bytes()) {
+            return Err((401, "Missing or invalid token".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn is_loopback_host(host: &str) -> bool {
+    // Strip the port, keeping IPv6 literals such as "[::1]:9100" intact
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) && !name.ends_with(':') => {
+            name
+        }
+        _ => host,
+    };
+    let name = name.trim_start_matches('[').trim_end_matches(']');
+    name.eq_ignore_ascii_case("localhost")
+        || name
+            .parse::<IpAddr>()
+            .is_ok_and(|address| address.is_loopback())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn route(method: &str, path: &str, body: &[u8]) -> Result<ControlCommand, (u16, String)> {
+    match (method, path) {
+        ("GET", "/status") => Ok(ControlCommand::Status),
+        ("POST", "/pause") => Ok(ControlCommand::Pause),
+        ("POST", "/resume") => Ok(ControlCommand::Resume),
+        ("POST", "/cancel_all") => Ok(ControlCommand::CancelAll),
+        ("POST", "/flatten") => Ok(ControlCommand::Flatten),
+        ("POST", "/config") => serde_json::from_slice(body)
+            .map(ControlCommand::UpdateConfig)
+            .map_err(|e| (400, format!("Invalid JSON body: {}", e))),
+        _ => Err((404, format!("No route for {} {}", method, path))),
+    }
+}
+
+async fn read_request<S>(stream: &mut BufReader<S>) -> Result<HttpRequest>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut request_line = String::new();
+    read_line(stream, &mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("Malformed request line");
+    };
+
+    let mut headers = HashMap::new();
+    for count in 0.. {
+        let mut header = String::new();
+        if read_line(stream, &mut header).await? == 0 || header.trim().is_empty() {
+            break;
+        }
+        if count >= MAX_HEADER_COUNT {
+            bail!("Too many headers");
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let content_length: usize = match headers.get("content-length") {
+        Some(value) => value.parse().context("Invalid Content-Length")?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_SIZE {
+        bail!("Request body too large");
+    }
+
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body).await?;
+    Ok(HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers,
+        body,
+    })
+}
+
+// read_line with a length limit, so a client can't grow the buffer without end
+async fn read_line<S>(stream: &mut BufReader<S>, line: &mut String) -> Result<usize>
+where
+    S: AsyncRead + Unpin,
+{
+    let read = (&mut *stream)
+        .take(MAX_LINE_LENGTH as u64 + 1)
+        .read_line(line)
+        .await?;
+    if read > MAX_LINE_LENGTH {
+        bail!("Request line or header too long");
+    }
+    Ok(read)
+}
+
+async fn write_response<S>(stream: &mut S, status: u16, body: &Value) -> Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    let body = serde_json::to_string_pretty(body)?;
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Positions, open orders, PnL and loop state of the running bot
+pub async fn status<C>(
+    resources: &InitResources<C>,
+    paused: bool,
+    last_error: Option<&ExecuteError>,
+) -> Result<Value> {
+    let ws_manager = &resources.ws_manager;
+    let mut positions = Vec::new();
+    let mut total_unrealized = 0.0;
+    for (coin, mut position) in ws_manager.get_positions().await {
+        position.pnl.unrealized = ws_manager.get_unrealized_pnl(&coin).await;
+        total_unrealized += position.pnl.unrealized;
+        positions.push(position);
+    }
+    let total_realized: f64 = positions.iter().map(|p| p.pnl.realized).sum();
+
+    let open_orders = resources
+        .http_client
+        .fetch_open_orders(resources.account_address)
+        .await?;
+
+    Ok(json!({
+        "coin": resources.config.coin,
+        "account_address": format!("{:?}", resources.account_address),
+        "paused": paused,
+        "last_execute_error": last_error,
+        "positions": positions,
+        "open_orders": open_orders,
+        "pnl": {
+            "realized": total_realized,
+            "unrealized": total_unrealized,
+        },
+    }))
+}
+
+/// Cancels every open order of the account
+pub async fn cancel_all<C>(resources: &InitResources<C>) -> Result<Value> {
+    let http_client = &resources.http_client;
+    let open_orders = http_client
+        .fetch_open_orders(resources.account_address)
+        .await?;
+
+    let mut cancelled = Vec::new();
+    let mut failed = Vec::new();
+    for order in open_orders {
+        match http_client
+            .cancel_order(order.coin.clone(), order.order_id)
+            .await
+        {
+            Ok(_) => cancelled.push(order.order_id),
+            Err(e) => {
+                error!("Failed to cancel order {}: {:?}", order.order_id, e);
+                failed.push(json!({ "order_id": order.order_id, "error": e.to_string() }));
+            }
+        }
+    }
+
+    info!("Cancelled {} orders via control server", cancelled.len());
+    Ok(json!({ "cancelled": cancelled, "failed": failed }))
+}
+
+/// Cancels all open orders and closes every position the exchange reports with reduce-only
+/// market orders. Positions come from the exchange, not from the fills seen since startup.
+pub async fn flatten<C>(resources: &InitResources<C>) -> Result<Value> {
+    let cancelled = cancel_all(resources).await?;
+
+    let http_client = &resources.http_client;
+    let mut closed = Vec::new();
+    let mut failed = Vec::new();
+    for symbol in http_client.fetch_open_positions().await? {
+        match http_client.close_position(&symbol).await {
+            Ok(Some(oid)) => closed.push(json!({ "coin": symbol, "order_id": oid })),
+            Ok(None) => {}
+            Err(e) => {
+                error!("Failed to close position in {}: {:?}", symbol, e);
+                failed.push(json!({ "coin": symbol, "error": e.to_string() }));
+            }
+        }
+    }
+
+    info!("Flattened {} positions via control server", closed.len());
+    Ok(json!({ "orders": cancelled, "closed": closed, "failed": failed }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn parse(raw: &[u8]) -> Result<HttpRequest> {
+        read_request(&mut BufReader::new(raw)).await
+    }
+
+    fn request(headers: &[(&str, &str)]) -> HttpRequest {
+        HttpRequest {
+            method: "GET".to_string(),
+            path: "/status".to_string(),
+            headers: headers
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            body: Vec::new(),
+        }
+    }
+
+    fn tcp_auth() -> ControlAuth {
+        ControlAuth {
+            token: Some("secret".to_string()),
+            loopback_host: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn read_request_parses_headers_and_body() {
+        let request =
+            parse(b"POST /config HTTP/1.1\r\nHost: localhost\r\nContent-Length: 2\r\n\r\n{}")
+                .await
+                .unwrap();
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/config")
+        );
+        assert_eq!(request.headers["host"], "localhost");
+        assert_eq!(request.body, b"{}");
+    }
+
+    #[tokio::test]
+    async fn read_request_limits_line_length() {
+        let long_path = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_LENGTH));
+        assert!(parse(long_path.as_bytes()).await.is_err());
+
+        let long_header = format!(
+            "GET / HTTP/1.1\r\nX-Filler: {}\r\n\r\n",
+            "a".repeat(MAX_LINE_LENGTH)
+        );
+        assert!(parse(long_header.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn read_request_limits_headers_and_body() {
+        let many_headers = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-Filler: a\r\n".repeat(MAX_HEADER_COUNT + 1)
+        );
+        assert!(parse(many_headers.as_bytes()).await.is_err());
+
+        let large_body = format!(
+            "POST /config HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_SIZE + 1
+        );
+        assert!(parse(large_body.as_bytes()).await.is_err());
+    }
+
+    #[test]
+    fn authorize_requires_the_token() {
+        let auth = tcp_auth();
+        assert!(authorize(&request(&[("authorization", "Bearer secret")]), &auth).is_ok());
+        for headers in [
+            &[][..],
+            &[("authorization", "Bearer wrong")][..],
+            &[("authorization", "secret")][..],
+        ] {
+            assert_eq!(authorize(&request(headers), &auth).unwrap_err().0, 401);
+        }
+    }
+
+    #[test]
+    fn authorize_rejects_browsers_and_foreign_hosts() {
+        let auth = tcp_auth();
+        let origin = request(&[
+            ("authorization", "Bearer secret"),
+            ("origin", "http://127.0.0.1:9100"),
+        ]);
+        assert_eq!(authorize(&origin, &auth).unwrap_err().0, 403);
+
+        let rebound = request(&[
+            ("authorization", "Bearer secret"),
+            ("host", "evil.example:9100"),
+        ]);
+        assert_eq!(authorize(&rebound, &auth).unwrap_err().0, 403);
+
+        let unix_auth = ControlAuth {
+            token: None,
+            loopback_host: false,
+        };
+        assert!(authorize(&request(&[("host", "evil.example")]), &unix_auth).is_ok());
+    }
+
+    #[test]
+    fn loopback_hosts() {
+        for host in [
+            "localhost",
+            "LOCALHOST:9100",
+            "127.0.0.1",
+            "127.0.0.1:9100",
+            "[::1]",
+            "[::1]:9100",
+        ] {
+            assert!(is_loopback_host(host), "{}", host);
+        }
+        for host in [
+            "evil.example",
+            "10.0.0.1:9100",
+            "localhost.evil.example",
+            "[::2]:9100",
+        ] {
+            assert!(!is_loopback_host(host), "{}", host);
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn unix_socket_never_replaces_other_files() {
+        let path = std::env::temp_dir().join(format!("control_rs_{}.sock", std::process::id()));
+        std::fs::write(&path, "keep me").unwrap();
+        let config = ControlConfig {
+            listen: None,
+            unix_socket: Some(path.clone()),
+            token: None,
+        };
+        assert!(spawn_control_server(&config).await.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep me");
+        std::fs::remove_file(&path).unwrap();
+
+        // A stale socket is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(spawn_control_server(&config).await.is_ok());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,47 +1,304 @@
-use crate::bot_framework::init::{initialize_bot, InitResources};
+use crate::bot_framework::alerts::AlertMonitor;
+use crate::bot_framework::config::Config;
+use crate::bot_framework::control::{
+    self, spawn_control_server, ControlCommand, ControlRequest, ExecuteError,
+};
+use crate::bot_framework::init::{initialize_bot, log_context, InitResources};
+use crate::bot_framework::reload::{apply_overrides, reload_config, ConfigWatcher};
+use crate::bot_framework::summary::{post_summary, SummaryPeriod, SummaryScheduler};
+use crate::hyperliquid::events::{EventChannel, EventFilter, MarketEvent};
+use crate::utils::logger::init_logging;
+use crate::utils::metrics::{spawn_metrics_server, EXECUTE_DURATION, EXECUTE_ERRORS};
+use crate::utils::notifier::{flush_alerts, init_notifiers};
 use anyhow::Result;
 use async_trait::async_trait;
+use chrono::Utc;
 use log::{error, info};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 use tokio::signal;
+use tokio::sync::mpsc::Receiver;
+use tokio::time::{interval, interval_at, Duration, Instant, MissedTickBehavior};
 
-/// Trait for defining the lifecycle of a trading bot
+const CONFIG_POLL_INTERVAL_SECS: u64 = 2;
+const HEALTH_CHECK_INTERVAL_SECS: u64 = 5;
+const ALERT_FLUSH_TIMEOUT_SECS: u64 = 5;
+const METADATA_REFRESH_INTERVAL_SECS: u64 = 600;
+
+/// Trait for defining the lifecycle of a trading bot.
+/// `C` is the bot's settings type, deserialized from the `bot_specific` section.
 #[async_trait]
-pub trait BotFramework {
+pub trait BotFramework<C: Send + Sync = Value> {
     /// Define required subscriptions
-    async fn subscribe(&mut self, resources: &InitResources) -> Result<()>;
+    async fn subscribe(&mut self, resources: &InitResources<C>) -> Result<()>;
 
     /// Define the main logic of the bot
-    async fn execute(&mut self, resources: &InitResources) -> Result<()>;
+    async fn execute(&mut self, resources: &InitResources<C>) -> Result<()>;
+
+    /// Called after the config file or the control server changed the config and the
+    /// new config passed validation. `resources` already holds the new config and
+    /// settings; returning an error restores `previous`.
+    async fn on_config_change(
+        &mut self,
+        _resources: &InitResources<C>,
+        _previous: &Config,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// State of the run loop that is visible through the control server
+#[derive(Default)]
+struct RunState {
+    paused: bool,
+    last_error: Option<ExecuteError>,
 }
 
 /// Main execution flow for running a bot
-pub async fn run_bot<B: BotFramework + Send + Sync>(mut bot: B, config_path: &str) -> Result<()> {
+pub async fn run_bot<B, C>(mut bot: B, config_path: &str) -> Result<()>
+where
+    B: BotFramework<C> + Send + Sync,
+    C: DeserializeOwned + Send + Sync,
+{
     // Initialize resources using the configuration file
-    let resources = initialize_bot(config_path).await?;
+    let mut resources = initialize_bot::<C>(config_path).await?;
 
     info!("Subscribing to necessary data...");
     bot.subscribe(&resources).await?;
 
+    let mut control_requests = match &resources.config.control {
+        Some(control_config) => Some(spawn_control_server(control_config).await?),
+        None => None,
+    };
+    if let Some(metrics_config) = &resources.config.metrics {
+        spawn_metrics_server(metrics_config).await?;
+    }
+
     let loop_interval_secs = resources.config.interval;
     info!("Loop interval: {} seconds", loop_interval_secs);
 
     info!("Starting the bot loop...");
-    let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(loop_interval_secs));
+    let mut loop_interval = interval(Duration::from_secs(loop_interval_secs));
+
+    let mut config_watcher = ConfigWatcher::new(config_path);
+    let mut config_poll = interval(Duration::from_secs(CONFIG_POLL_INTERVAL_SECS));
+    config_poll.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut health_check = interval(Duration::from_secs(HEALTH_CHECK_INTERVAL_SECS));
+    health_check.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    // HttpClient::new already loaded the metadata
+    let metadata_period = Duration::from_secs(METADATA_REFRESH_INTERVAL_SECS);
+    let mut metadata_refresh = interval_at(Instant::now() + metadata_period, metadata_period);
+    metadata_refresh.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    let mut state = RunState::default();
+    let mut summary_scheduler = new_summary_scheduler(&resources.config);
+
+    let mut alert_monitor = AlertMonitor::new();
+    let mut fill_events = resources
+        .ws_manager
+        .subscribe_events(EventFilter::new().channel(EventChannel::UserFills))
+        .await;
+    alert_monitor
+        .started(resources.config.alerts.as_ref(), &resources.config.coin)
+        .await;
 
     loop {
+        let interval_secs = resources.config.interval;
+        let summary_config = resources.config.summary.clone();
+        let notifiers = resources.config.notifiers.clone();
+        let logging = resources.config.logging.clone();
+
         tokio::select! {
-            _ = interval.tick() => {
-                if let Err(e) = bot.execute(&resources).await {
+            _ = loop_interval.tick() => {
+                if state.paused {
+                    continue;
+                }
+                let timer = EXECUTE_DURATION.start_timer();
+                let result = bot.execute(&resources).await;
+                timer.observe_duration();
+                let alerts = resources.config.alerts.as_ref();
+                if let Err(e) = result {
                     error!("Error executing bot: {:?}", e);
+                    EXECUTE_ERRORS.inc();
+                    let message = format!("{:?}", e);
+                    alert_monitor.execute_failed(alerts, &message).await;
+                    state.last_error = Some(ExecuteError {
+                        timestamp: Utc::now().timestamp_millis(),
+                        message,
+                    });
+                } else {
+                    alert_monitor.execute_succeeded(alerts).await;
+                }
+            }
+            _ = config_poll.tick() => {
+                if !config_watcher.poll() {
+                    continue;
+                }
+                match reload_config::<C>(config_path, &resources.config) {
+                    Ok((config, settings)) => {
+                        match apply_config(&mut bot, &mut resources, config, settings).await {
+                            Ok(()) => info!("Config reloaded from {}", config_path),
+                            Err(e) => error!("Bot rejected config change: {:?}", e),
+                        }
+                    }
+                    Err(e) => error!("Rejected config change: {:?}", e),
+                }
+            }
+            _ = health_check.tick() => {
+                alert_monitor
+                    .check_connection(resources.config.alerts.as_ref(), &resources.ws_manager)
+                    .await;
+                alert_monitor
+                    .check_drawdown(resources.config.alerts.as_ref(), &resources.ws_manager)
+                    .await;
+                if let Err(e) = resources.ws_manager.check_health(&resources.http_client).await {
+                    error!("Websocket health check failed: {:?}", e);
                 }
             }
+            _ = metadata_refresh.tick() => {
+                if let Err(e) = resources.http_client.refresh_metadata().await {
+                    error!("Failed to refresh asset metadata: {:?}", e);
+                }
+            }
+            period = next_summary(&mut summary_scheduler) => {
+                if let Some(config) = resources.config.summary.clone() {
+                    // Posting must not hold up the loop
+                    tokio::spawn(post_summary(
+                        resources.ws_manager.clone(),
+                        resources.db_client.clone(),
+                        resources.account_address,
+                        config,
+                        period,
+                    ));
+                }
+            }
+            Ok(MarketEvent::UserFill { fill, .. }) = fill_events.recv() => {
+                alert_monitor
+                    .on_fill(resources.config.alerts.as_ref(), &resources.ws_manager, &fill)
+                    .await;
+            }
+            Some(request) = next_control_request(&mut control_requests) => {
+                let ControlRequest { command, respond_to } = request;
+                info!("Control command: {:?}", command);
+                let result = handle_control_command(&mut bot, &mut resources, &mut state, command).await;
+                let _ = respond_to.send(result);
+            }
             _ = signal::ctrl_c() => {
                 info!("Shutdown signal received");
                 break;
             }
         }
+
+        if resources.config.interval != interval_secs {
+            info!("Loop interval: {} seconds", resources.config.interval);
+            loop_interval = interval(Duration::from_secs(resources.config.interval));
+        }
+        if resources.config.summary != summary_config {
+            summary_scheduler = new_summary_scheduler(&resources.config);
+        }
+        if resources.config.notifiers != notifiers {
+            init_notifiers(&resources.config.notifiers).await;
+        }
+        if resources.config.logging != logging {
+            if let Some(logging) = &resources.config.logging {
+                let context = log_context(&resources.config, config_path);
+                if let Err(e) = init_logging(logging, &context) {
+                    error!("Failed to apply logging config: {}", e);
+                }
+            }
+        }
     }
 
     info!("Bot stopped");
+    alert_monitor
+        .stopped(resources.config.alerts.as_ref(), "Shutdown signal received")
+        .await;
+    flush_alerts(Duration::from_secs(ALERT_FLUSH_TIMEOUT_SECS)).await;
     Ok(())
 }
+
+// Swaps in the new config and settings, restoring the previous ones if the bot rejects them
+async fn apply_config<B, C>(
+    bot: &mut B,
+    resources: &mut InitResources<C>,
+    config: Config,
+    settings: C,
+) -> Result<()>
+where
+    B: BotFramework<C> + Send + Sync,
+    C: Send + Sync,
+{
+    let previous_config = std::mem::replace(&mut resources.config, config);
+    let previous_settings = std::mem::replace(&mut resources.settings, settings);
+    if let Err(e) = bot.on_config_change(resources, &previous_config).await {
+        resources.config = previous_config;
+        resources.settings = previous_settings;
+        return Err(e);
+    }
+    Ok(())
+}
+
+async fn handle_control_command<B, C>(
+    bot: &mut B,
+    resources: &mut InitResources<C>,
+    state: &mut RunState,
+    command: ControlCommand,
+) -> Result<Value>
+where
+    B: BotFramework<C> + Send + Sync,
+    C: DeserializeOwned + Send + Sync,
+{
+    match command {
+        ControlCommand::Status => {
+            control::status(resources, state.paused, state.last_error.as_ref()).await
+        }
+        ControlCommand::Pause => {
+            state.paused = true;
+            info!("Bot loop paused");
+            Ok(json!({ "paused": true }))
+        }
+        ControlCommand::Resume => {
+            state.paused = false;
+            info!("Bot loop resumed");
+            Ok(json!({ "paused": false }))
+        }
+        ControlCommand::CancelAll => control::cancel_all(resources).await,
+        ControlCommand::Flatten => control::flatten(resources).await,
+        ControlCommand::UpdateConfig(overrides) => {
+            let (config, settings) = apply_overrides::<C>(&resources.config, &overrides)?;
+            apply_config(bot, resources, config, settings).await?;
+            info!("Config updated via control server");
+            Ok(json!({
+                "interval": resources.config.interval,
+                "bot_specific": resources.config.bot_specific,
+            }))
+        }
+    }
+}
+
+fn new_summary_scheduler(config: &Config) -> Option<SummaryScheduler> {
+    let summary = config.summary.as_ref()?;
+    match SummaryScheduler::new(summary) {
+        Ok(scheduler) => Some(scheduler),
+        Err(e) => {
+            error!("Summaries disabled: {:?}", e);
+            None
+        }
+    }
+}
+
+async fn next_summary(scheduler: &mut Option<SummaryScheduler>) -> SummaryPeriod {
+    match scheduler {
+        Some(scheduler) => scheduler.wait().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn next_control_request(
+    receiver: &mut Option<Receiver<ControlRequest>>,
+) -> Option<ControlRequest> {
+    match receiver {
+        Some(receiver) => receiver.recv().await,
+        None => std::future::pending().await,
+    }
+}
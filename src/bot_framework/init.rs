@@ -1,58 +1,75 @@
+use crate::bot_framework::config::Config;
+use crate::bot_framework::secret::SecretProvider;
+use crate::hyperliquid::db::{connect_storage, Storage};
 use crate::hyperliquid::http::HttpClient;
+use crate::hyperliquid::order_audit::OrderAuditor;
 use crate::hyperliquid::subscriptions::Subscription;
 use crate::hyperliquid::websocket::WebSocketManager;
-use anyhow::{Context, Result};
-use ethers::signers::{LocalWallet, Signer};
-use serde::Deserialize;
+use crate::utils::logger::{init_logging, LogContext};
+use crate::utils::notifier::init_notifiers;
+use anyhow::{anyhow, Context, Result};
+use ethers::signers::LocalWallet;
+use ethers::types::H160;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
-use std::fs;
-use std::str::FromStr;
+use std::path::Path;
 use std::sync::Arc;
-use tokio_postgres::{Client, NoTls};
 
-#[derive(Debug, Deserialize)]
-pub struct Config {
-    pub wallet_secret: String,
-    pub is_mainnet: bool,
-    pub coin: String,
-    pub interval: u64,
-    pub database_url: Option<String>,
-    #[serde(default)] // User empty object if bot_specific is missing
-    pub bot_specific: Value, // Bot-specific configuration
-}
-
-pub struct InitResources {
+pub struct InitResources<C = Value> {
     pub ws_manager: Arc<WebSocketManager>,
     pub http_client: HttpClient,
     pub wallet: LocalWallet,
+    pub account_address: H160,
     pub config: Config,
-    pub db_client: Option<Arc<Client>>,
+    pub settings: C, // Typed bot_specific settings
+    pub db_client: Option<Arc<dyn Storage>>,
 }
 
-pub async fn initialize_bot(config_path: &str) -> Result<InitResources> {
-    let config_content = fs::read_to_string(config_path).context("Failed to read config file")?;
-    let config: Config = toml::from_str(&config_content).context("Failed to parse config file")?;
+pub async fn initialize_bot<C: DeserializeOwned>(config_path: &str) -> Result<InitResources<C>> {
+    let config = Config::load(config_path)?;
+    let settings: C = config.bot_settings()?;
+    if let Some(logging) = &config.logging {
+        init_logging(logging, &log_context(&config, config_path))
+            .map_err(|e| anyhow!("Failed to set up logging: {}", e))?;
+    }
+    init_notifiers(&config.notifiers).await;
 
-    // let private_key = env::var("WALLET_SECRET").expect("WALLET_SECRET not set");
-    let wallet = LocalWallet::from_str(&config.wallet_secret).context("Invalid wallet secret")?;
+    let wallet = config
+        .wallet_secret
+        .load_wallet()
+        .context("Failed to load wallet secret")?;
 
     // Initialize database connection if database_url is provided
     let db_client = if let Some(database_url) = &config.database_url {
-        let (client, connection) = tokio_postgres::connect(database_url, NoTls).await?;
-        tokio::spawn(async move {
-            if let Err(e) = connection.await {
-                eprintln!("Connection error: {}", e);
-            }
-        });
-        Some(Arc::new(client))
+        let storage = connect_storage(database_url).await?;
+        storage
+            .migrate()
+            .await
+            .context("Failed to migrate database")?;
+        Some(storage)
     } else {
         None
     };
 
-    let http_client = HttpClient::new(config.is_mainnet, wallet.clone()).await?;
-    let asset_info = http_client.get_asset_info(&config.coin).unwrap();
+    let mut http_client =
+        HttpClient::new(config.is_mainnet, wallet.clone(), config.vault_address).await?;
+    if let (None, Some(account_address)) = (config.vault_address, config.account_address) {
+        // wallet_secret belongs to an approved agent which only signs for account_address
+        http_client = http_client.with_account_address(account_address);
+    }
+    if let Some(rate_limit) = &config.rate_limit {
+        http_client = http_client.with_rate_limit(rate_limit.clone());
+    }
+    if let Some(retry) = &config.retry {
+        http_client = http_client.with_retry(retry.clone());
+    }
+    let account_address = http_client.account_address();
+    http_client =
+        http_client.with_order_auditor(OrderAuditor::spawn(db_client.clone(), account_address));
+    let asset_info = http_client.resolve_asset(&config.coin)?;
 
     let ws_manager = WebSocketManager::new(config.is_mainnet, db_client.clone()).await;
+    ws_manager.set_account_address(account_address).await;
 
     // Subscribe to necessary data
     ws_manager.subscribe(Subscription::AllMids).await?;
@@ -68,7 +85,7 @@ pub async fn initialize_bot(config_path: &str) -> Result<InitResources> {
         .await?;
     ws_manager
         .subscribe(Subscription::UserFills {
-            user: wallet.address(),
+            user: account_address,
         })
         .await?;
 
@@ -76,7 +93,26 @@ pub async fn initialize_bot(config_path: &str) -> Result<InitResources> {
         ws_manager,
         http_client,
         wallet,
+        account_address,
         config,
+        settings,
         db_client,
     })
 }
+
+/// Bot name and coin attached to JSON log lines. The bot name defaults to the config file name.
+pub fn log_context(config: &Config, config_path: &str) -> LogContext {
+    let bot = config
+        .logging
+        .as_ref()
+        .and_then(|logging| logging.bot_name.clone())
+        .or_else(|| {
+            Path::new(config_path)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+        });
+    LogContext {
+        bot,
+        coin: Some(config.coin.clone()),
+    }
+}
@@ -1,3 +1,9 @@
+pub mod alerts;
 pub mod common;
+pub mod config;
+pub mod control;
 pub mod framework;
 pub mod init;
+pub mod reload;
+pub mod secret;
+pub mod summary;
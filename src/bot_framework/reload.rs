@@ -0,0 +1,137 @@
+use crate::bot_framework::config::Config;
+use anyhow::{bail, Result};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+/// Detects modifications of the config files by polling their modification times
+pub struct ConfigWatcher {
+    paths: Vec<PathBuf>,
+    last_modified: Vec<Option<SystemTime>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config_path: &str) -> Self {
+        let paths = Config::source_paths(config_path);
+        let last_modified = paths.iter().map(modified_time).collect();
+        Self {
+            paths,
+            last_modified,
+        }
+    }
+
+    /// Returns true once per modification of any watched file
+    pub fn poll(&mut self) -> bool {
+        let current: Vec<Option<SystemTime>> = self.paths.iter().map(modified_time).collect();
+        if current != self.last_modified {
+            self.last_modified = current;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Loads and validates the config again and rejects changes to fields that
+/// can only be applied by restarting the bot.
+pub fn reload_config<C: DeserializeOwned>(
+    config_path: &str,
+    current: &Config,
+) -> Result<(Config, C)> {
+    let config = Config::load(config_path)?;
+
+    let changed = immutable_changes(current, &config);
+    if !changed.is_empty() {
+        bail!(
+            "Changes to {} require a restart, config not applied",
+            changed.join(", ")
+        );
+    }
+
+    let settings = config.bot_settings()?;
+    Ok((config, settings))
+}
+
+/// Applies parameter changes sent through the control server. Only `interval` and
+/// `bot_specific` can change at runtime, `bot_specific` is merged key by key.
+pub fn apply_overrides<C: DeserializeOwned>(
+    current: &Config,
+    overrides: &Value,
+) -> Result<(Config, C)> {
+    let Some(overrides) = overrides.as_object() else {
+        bail!("Config overrides must be a JSON object");
+    };
+
+    let mut config = current.clone();
+    let mut errors = Vec::new();
+    for (key, value) in overrides {
+        match key.as_str() {
+            "interval" => match value.as_u64() {
+                Some(interval) => config.interval = interval,
+                None => errors.push("interval: must be a positive integer".to_string()),
+            },
+            "bot_specific" => merge_json(&mut config.bot_specific, value.clone()),
+            _ => errors.push(format!("{}: cannot be changed at runtime", key)),
+        }
+    }
+
+    errors.extend(config.validate());
+    if !errors.is_empty() {
+        bail!("Invalid configuration:\n  - {}", errors.join("\n  - "));
+    }
+
+    let settings = config.bot_settings()?;
+    Ok((config, settings))
+}
+
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge_json(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn immutable_changes(current: &Config, new: &Config) -> Vec<&'static str> {
+    let mut changed = Vec::new();
+    if current.wallet_secret != new.wallet_secret {
+        changed.push("wallet_secret");
+    }
+    if current.is_mainnet != new.is_mainnet {
+        changed.push("is_mainnet");
+    }
+    if current.coin != new.coin {
+        changed.push("coin");
+    }
+    if current.database_url != new.database_url {
+        changed.push("database_url");
+    }
+    if current.vault_address != new.vault_address {
+        changed.push("vault_address");
+    }
+    if current.account_address != new.account_address {
+        changed.push("account_address");
+    }
+    if current.control != new.control {
+        changed.push("control");
+    }
+    if current.metrics != new.metrics {
+        changed.push("metrics");
+    }
+    if current.rate_limit != new.rate_limit {
+        changed.push("rate_limit");
+    }
+    if current.retry != new.retry {
+        changed.push("retry");
+    }
+    changed
+}
+
+fn modified_time(path: &PathBuf) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
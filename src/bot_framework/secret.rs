@@ -0,0 +1,336 @@
+use anyhow::{bail, Context, Result};
+use ethers::signers::LocalWallet;
+use log::warn;
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const DEFAULT_SECRET_ENV: &str = "WALLET_SECRET";
+const DEFAULT_PASSPHRASE_ENV: &str = "KEYSTORE_PASSPHRASE";
+
+/// Source of the private key used to sign orders
+pub trait SecretProvider {
+    fn load_wallet(&self) -> Result<LocalWallet>;
+}
+
+/// Reads a hex private key from an environment variable
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnvSecret {
+    pub env: String,
+}
+
+impl EnvSecret {
+    pub fn read(&self) -> Result<String> {
+        let secret = env::var(&self.env).with_context(|| format!("{} not set", self.env))?;
+        Ok(secret.trim().to_string())
+    }
+}
+
+impl SecretProvider for EnvSecret {
+    fn load_wallet(&self) -> Result<LocalWallet> {
+        LocalWallet::from_str(&self.read()?).context("Invalid wallet secret")
+    }
+}
+
+/// Reads a hex private key from a file that must only be accessible by its owner
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileSecret {
+    pub file: PathBuf,
+}
+
+impl FileSecret {
+    pub fn read(&self) -> Result<String> {
+        check_permissions(&self.file)?;
+        let secret = fs::read_to_string(&self.file)
+            .with_context(|| format!("Failed to read secret file {}", self.file.display()))?;
+        Ok(secret.trim().to_string())
+    }
+}
+
+impl SecretProvider for FileSecret {
+    fn load_wallet(&self) -> Result<LocalWallet> {
+        LocalWallet::from_str(&self.read()?).context("Invalid wallet secret")
+    }
+}
+
+/// Decrypts an encrypted JSON keystore with a passphrase taken from an environment variable
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KeystoreSecret {
+    pub keystore: PathBuf,
+    #[serde(default = "default_passphrase_env")]
+    pub passphrase_env: String,
+}
+
+impl SecretProvider for KeystoreSecret {
+    fn load_wallet(&self) -> Result<LocalWallet> {
+        check_permissions(&self.keystore)?;
+        let passphrase = env::var(&self.passphrase_env)
+            .with_context(|| format!("{} not set", self.passphrase_env))?;
+        LocalWallet::decrypt_keystore(&self.keystore, passphrase)
+            .with_context(|| format!("Failed to decrypt keystore {}", self.keystore.display()))
+    }
+}
+
+/// `wallet_secret` setting of the bot config. Accepts a plain hex key for backwards
+/// compatibility, or a table selecting one of the providers above:
+///
+/// ```toml
+/// wallet_secret = { env = "WALLET_SECRET" }
+/// wallet_secret = { file = "/etc/bot/wallet.key" }
+/// wallet_secret = { keystore = "/etc/bot/keystore.json", passphrase_env = "KEYSTORE_PASSPHRASE" }
+/// ```
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum WalletSecret {
+    Plain(String),
+    Env(EnvSecret),
+    File(FileSecret),
+    Keystore(KeystoreSecret),
+}
+
+impl Default for WalletSecret {
+    fn default() -> Self {
+        WalletSecret::Env(EnvSecret {
+            env: DEFAULT_SECRET_ENV.to_string(),
+        })
+    }
+}
+
+impl SecretProvider for WalletSecret {
+    fn load_wallet(&self) -> Result<LocalWallet> {
+        match self {
+            WalletSecret::Plain(secret) => {
+                warn!("wallet_secret is stored in plaintext, consider env, file or keystore");
+                LocalWallet::from_str(secret).context("Invalid wallet secret")
+            }
+            WalletSecret::Env(provider) => provider.load_wallet(),
+            WalletSecret::File(provider) => provider.load_wallet(),
+            WalletSecret::Keystore(provider) => provider.load_wallet(),
+        }
+    }
+}
+
+// Never print the key itself
+impl fmt::Debug for WalletSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalletSecret::Plain(_) => f.write_str("Plain(<redacted>)"),
+            WalletSecret::Env(provider) => f.debug_tuple("Env").field(provider).finish(),
+            WalletSecret::File(provider) => f.debug_tuple("File").field(provider).finish(),
+            WalletSecret::Keystore(provider) => f.debug_tuple("Keystore").field(provider).finish(),
+        }
+    }
+}
+
+/// A shared secret other than the wallet key, e.g. `control.token`:
+///
+/// ```toml
+/// token = { env = "CONTROL_TOKEN" }
+/// token = { file = "/etc/bot/control.token" }
+/// ```
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum TokenSecret {
+    Plain(String),
+    Env(EnvSecret),
+    File(FileSecret),
+}
+
+impl TokenSecret {
+    pub fn read(&self) -> Result<String> {
+        let token = match self {
+            TokenSecret::Plain(token) => token.clone(),
+            TokenSecret::Env(provider) => provider.read()?,
+            TokenSecret::File(provider) => provider.read()?,
+        };
+        if token.is_empty() {
+            bail!("Token is empty");
+        }
+        Ok(token)
+    }
+}
+
+impl fmt::Debug for TokenSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenSecret::Plain(_) => f.write_str("Plain(<redacted>)"),
+            TokenSecret::Env(provider) => f.debug_tuple("Env").field(provider).finish(),
+            TokenSecret::File(provider) => f.debug_tuple("File").field(provider).finish(),
+        }
+    }
+}
+
+fn default_passphrase_env() -> String {
+    DEFAULT_PASSPHRASE_ENV.to_string()
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let metadata =
+        fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    let mode = metadata.permissions().mode();
+    if mode & 0o077 != 0 {
+        bail!(
+            "{} is accessible by group or others (mode {:o}), run `chmod 600` on it",
+            path.display(),
+            mode & 0o777
+        );
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(path: &Path) -> Result<()> {
+    if !path.exists() {
+        bail!("{} does not exist", path.display());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::Signer;
+
+    const KEY: &str = "4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+
+    #[derive(Debug, Deserialize)]
+    struct Settings {
+        wallet_secret: WalletSecret,
+    }
+
+    fn parse(value: &str) -> Result<WalletSecret, toml::de::Error> {
+        toml::from_str::<Settings>(&format!("wallet_secret = {}", value))
+            .map(|settings| settings.wallet_secret)
+    }
+
+    fn secret_file(name: &str, contents: &str, mode: u32) -> PathBuf {
+        let path = env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+        fs::write(&path, contents).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn wallet_secret_variants() {
+        assert_eq!(
+            parse(&format!("\"{}\"", KEY)).unwrap(),
+            WalletSecret::Plain(KEY.to_string())
+        );
+        assert_eq!(
+            parse("{ env = \"BOT_KEY\" }").unwrap(),
+            WalletSecret::Env(EnvSecret {
+                env: "BOT_KEY".to_string()
+            })
+        );
+        assert_eq!(
+            parse("{ file = \"/etc/bot/wallet.key\" }").unwrap(),
+            WalletSecret::File(FileSecret {
+                file: PathBuf::from("/etc/bot/wallet.key")
+            })
+        );
+        assert_eq!(
+            parse("{ keystore = \"/etc/bot/keystore.json\" }").unwrap(),
+            WalletSecret::Keystore(KeystoreSecret {
+                keystore: PathBuf::from("/etc/bot/keystore.json"),
+                passphrase_env: DEFAULT_PASSPHRASE_ENV.to_string(),
+            })
+        );
+        assert_eq!(
+            parse("{ keystore = \"k.json\", passphrase_env = \"PASS\" }").unwrap(),
+            WalletSecret::Keystore(KeystoreSecret {
+                keystore: PathBuf::from("k.json"),
+                passphrase_env: "PASS".to_string(),
+            })
+        );
+        assert_eq!(
+            WalletSecret::default(),
+            parse("{ env = \"WALLET_SECRET\" }").unwrap()
+        );
+    }
+
+    #[test]
+    fn wallet_secret_rejects_unknown_fields() {
+        assert!(parse("{ env = \"BOT_KEY\", file = \"wallet.key\" }").is_err());
+        assert!(parse("{ path = \"wallet.key\" }").is_err());
+    }
+
+    #[test]
+    fn debug_output_redacts_plain_secrets() {
+        let wallet = format!("{:?}", WalletSecret::Plain(KEY.to_string()));
+        assert_eq!(wallet, "Plain(<redacted>)");
+        let token = format!("{:?}", TokenSecret::Plain("hunter2".to_string()));
+        assert!(!token.contains("hunter2"));
+        let env = format!(
+            "{:?}",
+            WalletSecret::Env(EnvSecret {
+                env: "BOT_KEY".to_string()
+            })
+        );
+        assert!(env.contains("BOT_KEY"));
+    }
+
+    #[test]
+    fn env_secret_loads_wallet() {
+        let name = "SECRET_RS_TEST_WALLET_KEY";
+        env::set_var(name, format!(" {}\n", KEY));
+        let secret = EnvSecret {
+            env: name.to_string(),
+        };
+        let wallet = secret.load_wallet().unwrap();
+        assert_eq!(
+            wallet.address(),
+            LocalWallet::from_str(KEY).unwrap().address()
+        );
+        env::remove_var(name);
+        assert!(secret.load_wallet().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn file_secret_requires_owner_only_permissions() {
+        let path = secret_file("secret_rs_owner_only", KEY, 0o600);
+        let secret = FileSecret { file: path.clone() };
+        assert!(secret.load_wallet().is_ok());
+
+        for mode in [0o640, 0o604, 0o644, 0o660] {
+            fs::set_permissions(&path, std::os::unix::fs::PermissionsExt::from_mode(mode)).unwrap();
+            let error = secret.load_wallet().unwrap_err().to_string();
+            assert!(error.contains("accessible by group or others"), "{}", error);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn missing_secret_file_is_an_error() {
+        let secret = FileSecret {
+            file: env::temp_dir().join("secret_rs_does_not_exist"),
+        };
+        assert!(secret.read().is_err());
+    }
+
+    #[test]
+    fn empty_token_is_rejected() {
+        assert!(TokenSecret::Plain(String::new()).read().is_err());
+        let path = secret_file("secret_rs_token", "token\n", 0o600);
+        assert_eq!(
+            TokenSecret::File(FileSecret { file: path.clone() })
+                .read()
+                .unwrap(),
+            "token"
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
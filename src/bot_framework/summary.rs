@@ -0,0 +1,293 @@
+use crate::hyperliquid::chart::{self, ChartOptions};
+use crate::hyperliquid::db::{load_fills_from_db_with_time_filter, Storage};
+use crate::hyperliquid::model::CustomUserFills;
+use crate::hyperliquid::report::{PnlReport, ReportOptions};
+use crate::hyperliquid::websocket::WebSocketManager;
+use crate::utils::discord::{DiscordMessage, DiscordNotifier, Embed};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, FixedOffset, NaiveTime, TimeZone, Utc};
+use ethers::types::H160;
+use log::{error, info, warn};
+use serde::Deserialize;
+use std::sync::Arc;
+
+const TIME_FORMAT: &str = "%H:%M";
+const CHART_FILENAME: &str = "pnl_chart.png";
+const COLOR_PROFIT: u32 = 0x26a69a;
+const COLOR_LOSS: u32 = 0xef5350;
+
+/// `[summary]` section of the bot config: PnL summaries posted to a Discord webhook
+/// at `daily_at` times (e.g. ["09:00"]) and/or at the top of every hour.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SummaryConfig {
+    pub webhook_url: String,
+    #[serde(default)]
+    pub daily_at: Vec<String>, // "HH:MM" in utc_offset_hours
+    #[serde(default)]
+    pub hourly: bool,
+    #[serde(default)]
+    pub utc_offset_hours: i32,
+    #[serde(default = "default_chart")]
+    pub chart: bool, // Attach the cumulative PnL chart
+}
+
+fn default_chart() -> bool {
+    true
+}
+
+impl SummaryConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if !self.webhook_url.starts_with("https://") {
+            errors.push("summary.webhook_url: must start with https://".to_string());
+        }
+        for time in &self.daily_at {
+            if NaiveTime::parse_from_str(time, TIME_FORMAT).is_err() {
+                errors.push(format!("summary.daily_at: {:?} is not HH:MM", time));
+            }
+        }
+        if self.daily_at.is_empty() && !self.hourly {
+            errors.push("summary: set daily_at or hourly".to_string());
+        }
+        if !(-12..=14).contains(&self.utc_offset_hours) {
+            errors.push("summary.utc_offset_hours: must be between -12 and 14".to_string());
+        }
+        errors
+    }
+
+    fn utc_offset(&self) -> FixedOffset {
+        FixedOffset::east_opt(self.utc_offset_hours * 3600)
+            .unwrap_or(FixedOffset::east_opt(0).unwrap())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SummaryPeriod {
+    Hourly,
+    Daily,
+}
+
+impl SummaryPeriod {
+    fn duration(self) -> Duration {
+        match self {
+            SummaryPeriod::Hourly => Duration::hours(1),
+            SummaryPeriod::Daily => Duration::days(1),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SummaryPeriod::Hourly => "Hourly",
+            SummaryPeriod::Daily => "Daily",
+        }
+    }
+}
+
+pub struct SummaryScheduler {
+    daily_at: Vec<NaiveTime>,
+    hourly: bool,
+    utc_offset: FixedOffset,
+    next: (DateTime<Utc>, SummaryPeriod),
+}
+
+impl SummaryScheduler {
+    pub fn new(config: &SummaryConfig) -> Result<Self> {
+        let daily_at = config
+            .daily_at
+            .iter()
+            .map(|time| {
+                NaiveTime::parse_from_str(time, TIME_FORMAT)
+                    .map_err(|_| anyhow!("Invalid summary time {:?}", time))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut scheduler = Self {
+            daily_at,
+            hourly: config.hourly,
+            utc_offset: config.utc_offset(),
+            next: (Utc::now(), SummaryPeriod::Daily),
+        };
+        scheduler.next = scheduler
+            .next_after(Utc::now())
+            .ok_or_else(|| anyhow!("Summary has no schedule"))?;
+        Ok(scheduler)
+    }
+
+    /// Waits for the next scheduled summary. Dropping the future keeps the schedule.
+    pub async fn wait(&mut self) -> SummaryPeriod {
+        let (at, period) = self.next;
+        let delay = (at - Utc::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(delay).await;
+        if let Some(next) = self.next_after(at) {
+            self.next = next;
+        }
+        period
+    }
+
+    // A daily summary replaces the hourly one due at the same time
+    fn next_after(&self, now: DateTime<Utc>) -> Option<(DateTime<Utc>, SummaryPeriod)> {
+        let local = now.with_timezone(&self.utc_offset);
+
+        let daily = self
+            .daily_at
+            .iter()
+            .filter_map(|&time| {
+                let today = self
+                    .utc_offset
+                    .from_local_datetime(&local.date_naive().and_time(time))
+                    .single()?
+                    .with_timezone(&Utc);
+                Some(if today > now {
+                    today
+                } else {
+                    today + Duration::days(1)
+                })
+            })
+            .min()
+            .map(|at| (at, SummaryPeriod::Daily));
+
+        let hourly = self.hourly.then(|| {
+            let hour_ms = Duration::hours(1).num_milliseconds();
+            let offset_ms = self.utc_offset.local_minus_utc() as i64 * 1000;
+            let next_ms = ((now.timestamp_millis() + offset_ms).div_euclid(hour_ms) + 1) * hour_ms
+                - offset_ms;
+            (
+                Utc.timestamp_millis_opt(next_ms).single().unwrap_or(now),
+                SummaryPeriod::Hourly,
+            )
+        });
+
+        match (daily, hourly) {
+            (Some(daily), Some(hourly)) if hourly.0 < daily.0 => Some(hourly),
+            (Some(daily), _) => Some(daily),
+            (None, hourly) => hourly,
+        }
+    }
+}
+
+/// Posts the summary of the period that just ended. Fills come from the database when
+/// one is configured, otherwise from the fills the websocket has kept in memory.
+pub async fn post_summary(
+    ws_manager: Arc<WebSocketManager>,
+    storage: Option<Arc<dyn Storage>>,
+    user: H160,
+    config: SummaryConfig,
+    period: SummaryPeriod,
+) {
+    let end_time = Utc::now();
+    let start_time = end_time - period.duration();
+    let fills = match load_fills(&ws_manager, storage, user, start_time, end_time).await {
+        Ok(fills) => fills,
+        Err(e) => {
+            error!("Failed to load fills for summary: {:?}", e);
+            return;
+        }
+    };
+
+    let utc_offset = config.utc_offset();
+    let report = PnlReport::from_fills(&fills, &ReportOptions::default().utc_offset(utc_offset));
+
+    let mut unrealized_pnl = 0.0;
+    let mut positions = Vec::new();
+    for (coin, position) in ws_manager.get_positions().await {
+        if position.amount == 0.0 {
+            continue;
+        }
+        let unrealized = ws_manager.get_unrealized_pnl(&coin).await;
+        unrealized_pnl += unrealized;
+        positions.push(format!(
+            "{}: {:.4} @ {:.4} ({:+.2})",
+            coin, position.amount, position.average_price, unrealized
+        ));
+    }
+
+    let range = format!(
+        "{} - {}",
+        start_time
+            .with_timezone(&utc_offset)
+            .format("%Y-%m-%d %H:%M"),
+        end_time.with_timezone(&utc_offset).format("%Y-%m-%d %H:%M")
+    );
+    let mut embed = Embed::new(&format!("{} summary", period.label()))
+        .description(&range)
+        .color(if report.total.net_pnl >= 0.0 {
+            COLOR_PROFIT
+        } else {
+            COLOR_LOSS
+        })
+        .field(
+            "Realized PnL",
+            &format!("{:.2}", report.total.realized_pnl),
+            true,
+        )
+        .field("Unrealized PnL", &format!("{:.2}", unrealized_pnl), true)
+        .field("Fees", &format!("{:.2}", report.total.fees), true)
+        .field("Net PnL", &format!("{:.2}", report.total.net_pnl), true)
+        .field(
+            "Fills",
+            &format!("{} ({:.2} volume)", report.total.fills, report.total.volume),
+            true,
+        )
+        .field(
+            "Positions",
+            &if positions.is_empty() {
+                "None".to_string()
+            } else {
+                positions.join("\n")
+            },
+            false,
+        )
+        .timestamp(&end_time.to_rfc3339());
+
+    let mut message = DiscordMessage::new();
+    if config.chart && !fills.is_empty() {
+        match render_chart(fills).await {
+            Ok(chart) => {
+                embed = embed.attachment_image(CHART_FILENAME);
+                message = message.attachment(CHART_FILENAME, chart);
+            }
+            Err(e) => warn!("Failed to render summary chart: {}", e),
+        }
+    }
+    let message = message.embed(embed);
+
+    match DiscordNotifier::new(&config.webhook_url)
+        .send(&message)
+        .await
+        .map_err(|e| e.to_string())
+    {
+        Ok(()) => info!("Posted {} summary", period.label().to_lowercase()),
+        Err(e) => error!("Failed to post summary: {}", e),
+    }
+}
+
+async fn load_fills(
+    ws_manager: &WebSocketManager,
+    storage: Option<Arc<dyn Storage>>,
+    user: H160,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<CustomUserFills>> {
+    let (start_time, end_time) = (start_time.timestamp_millis(), end_time.timestamp_millis());
+    match storage {
+        Some(storage) => {
+            load_fills_from_db_with_time_filter(storage.as_ref(), user, start_time, end_time).await
+        }
+        None => Ok(ws_manager
+            .get_user_fills()
+            .await
+            .into_iter()
+            .filter(|fill| (start_time..=end_time).contains(&fill.timestamp))
+            .collect()),
+    }
+}
+
+// Plotters drawing and PNG encoding are blocking
+async fn render_chart(fills: Vec<CustomUserFills>) -> Result<Vec<u8>, String> {
+    tokio::task::spawn_blocking(move || {
+        chart::render_pnl_chart(&fills, &ChartOptions::default()).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
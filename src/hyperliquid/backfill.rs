@@ -0,0 +1,110 @@
+use crate::hyperliquid::db::{load_fills_from_db_with_time_filter, save_fills_to_db, Storage};
+use crate::hyperliquid::http::HttpClient;
+use anyhow::Result;
+use ethers::types::H160;
+use log::info;
+
+const SAVE_CHUNK_SIZE: usize = 500;
+
+/// Ranges within `start_time..=end_time` where consecutive stored fills are more than
+/// `min_gap_ms` apart, including the edges of the range. Boundary fills are part of the
+/// ranges so nothing between them is missed; refetching them is harmless.
+pub fn find_fill_gaps(
+    timestamps: &[i64],
+    start_time: i64,
+    end_time: i64,
+    min_gap_ms: i64,
+) -> Vec<(i64, i64)> {
+    let mut points = Vec::with_capacity(timestamps.len() + 2);
+    points.push(start_time);
+    points.extend(
+        timestamps
+            .iter()
+            .copied()
+            .filter(|&time| start_time <= time && time <= end_time),
+    );
+    points.push(end_time);
+    points.sort_unstable();
+
+    points
+        .windows(2)
+        .filter(|pair| pair[1] - pair[0] > min_gap_ms)
+        .map(|pair| (pair[0], pair[1]))
+        .collect()
+}
+
+/// Fetches the fills of `user` for every gap in the stored fills between `start_time` and
+/// `end_time` (milliseconds) and upserts them. Safe to run repeatedly.
+/// Returns the number of fills fetched.
+pub async fn backfill_user_fills(
+    http_client: &HttpClient,
+    storage: &dyn Storage,
+    user: H160,
+    start_time: i64,
+    end_time: i64,
+    min_gap_ms: i64,
+) -> Result<usize> {
+    let stored = load_fills_from_db_with_time_filter(storage, user, start_time, end_time).await?;
+    let timestamps: Vec<i64> = stored.iter().map(|fill| fill.timestamp).collect();
+
+    let mut total = 0;
+    for (gap_start, gap_end) in find_fill_gaps(&timestamps, start_time, end_time, min_gap_ms) {
+        let fills = http_client
+            .fetch_user_fills_by_time(user, gap_start.max(0) as u64, gap_end.max(0) as u64)
+            .await?;
+        for chunk in fills.chunks(SAVE_CHUNK_SIZE) {
+            save_fills_to_db(storage, chunk, user).await?;
+        }
+        if !fills.is_empty() {
+            info!(
+                "Backfilled {} fills of {:?} between {} and {}",
+                fills.len(),
+                user,
+                gap_start,
+                gap_end
+            );
+        }
+        total += fills.len();
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_fills_is_one_gap() {
+        assert_eq!(find_fill_gaps(&[], 0, 1000, 100), vec![(0, 1000)]);
+    }
+
+    #[test]
+    fn dense_fills_have_no_gaps() {
+        assert!(find_fill_gaps(&[50, 150, 250], 0, 300, 100).is_empty());
+    }
+
+    #[test]
+    fn gaps_include_boundary_fills_and_range_edges() {
+        assert_eq!(
+            find_fill_gaps(&[100, 150, 600, 650], 0, 1000, 100),
+            vec![(150, 600), (650, 1000)]
+        );
+        assert_eq!(
+            find_fill_gaps(&[500], 0, 1000, 100),
+            vec![(0, 500), (500, 1000)]
+        );
+    }
+
+    #[test]
+    fn gap_of_exactly_min_gap_is_ignored() {
+        assert!(find_fill_gaps(&[100], 0, 200, 100).is_empty());
+    }
+
+    #[test]
+    fn unsorted_and_out_of_range_fills() {
+        assert_eq!(
+            find_fill_gaps(&[-500, 900, 100, 5000], 0, 1000, 100),
+            vec![(100, 900)]
+        );
+    }
+}
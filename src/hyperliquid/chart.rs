@@ -0,0 +1,824 @@
+use crate::hyperliquid::model::{CustomCandle, CustomL2Book, CustomLevel, CustomUserFills};
+use crate::hyperliquid::report::{fee_in_quote, EquityPoint};
+use chrono::{DateTime, Local, TimeZone};
+use plotters::coord::types::{RangedCoordf64, RangedDateTime};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct ChartTheme {
+    pub background: RGBColor,
+    /// Text, axes and legend borders
+    pub foreground: RGBColor,
+    pub grid: RGBColor,
+    /// Rising candles, buys and bids
+    pub up: RGBColor,
+    /// Falling candles, sells and asks
+    pub down: RGBColor,
+    /// Line colors for one series per coin, reused in order. The default palette is
+    /// used when empty.
+    pub palette: Vec<RGBColor>,
+}
+
+impl ChartTheme {
+    pub fn dark() -> Self {
+        Self {
+            background: RGBColor(30, 30, 30),
+            foreground: WHITE,
+            grid: RGBColor(60, 60, 60),
+            up: RGBColor(38, 166, 154),
+            down: RGBColor(239, 83, 80),
+            palette: default_palette(),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            background: WHITE,
+            foreground: RGBColor(33, 33, 33),
+            grid: RGBColor(225, 225, 225),
+            up: RGBColor(0, 150, 136),
+            down: RGBColor(229, 57, 53),
+            palette: default_palette(),
+        }
+    }
+
+    fn color(&self, i: usize) -> RGBColor {
+        if self.palette.is_empty() {
+            let palette = default_palette();
+            return palette[i % palette.len()];
+        }
+        self.palette[i % self.palette.len()]
+    }
+}
+
+impl Default for ChartTheme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+// Okabe-Ito, distinguishable with color vision deficiencies
+fn default_palette() -> Vec<RGBColor> {
+    vec![
+        RGBColor(0, 114, 178),   // Blue
+        RGBColor(0, 158, 115),   // Green
+        RGBColor(213, 94, 0),    // Orange
+        RGBColor(204, 121, 167), // Purple
+        RGBColor(230, 159, 0),   // Yellow
+        RGBColor(86, 180, 233),  // Sky blue
+        RGBColor(240, 228, 66),  // Lemon
+    ]
+}
+
+/// Size, theme and title of a chart. The output format follows the extension of the path,
+/// `.svg` for SVG and anything else for PNG.
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub width: u32,
+    pub height: u32,
+    pub theme: ChartTheme,
+    pub title: Option<String>,
+}
+
+impl Default for ChartOptions {
+    fn default() -> Self {
+        Self {
+            width: 1024,
+            height: 768,
+            theme: ChartTheme::default(),
+            title: None,
+        }
+    }
+}
+
+impl ChartOptions {
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn theme(mut self, theme: ChartTheme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_string());
+        self
+    }
+
+    fn caption<'a>(&'a self, default: &'a str) -> &'a str {
+        self.title.as_deref().unwrap_or(default)
+    }
+}
+
+// Draws with the backend matching the extension of the path
+macro_rules! render {
+    ($path:expr, $options:expr, $draw:ident($($arg:expr),*)) => {{
+        let path: &Path = $path.as_ref();
+        let size = ($options.width, $options.height);
+        if path.extension().is_some_and(|extension| extension == "svg") {
+            let root = SVGBackend::new(path, size).into_drawing_area();
+            $draw(&root, $options, $($arg),*)?;
+            root.present()?;
+        } else {
+            let root = BitMapBackend::new(path, size).into_drawing_area();
+            $draw(&root, $options, $($arg),*)?;
+            root.present()?;
+        }
+        Ok(())
+    }};
+}
+
+// PNG bytes drawn into an RGB buffer instead of a file
+fn render_png(
+    options: &ChartOptions,
+    draw: impl FnOnce(&DrawingArea<BitMapBackend, Shift>) -> Result<(), Box<dyn Error>>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = vec![0u8; options.width as usize * options.height as usize * 3];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (options.width, options.height))
+            .into_drawing_area();
+        draw(&root)?;
+        root.present()?;
+    }
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, options.width, options.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&buffer)?;
+    Ok(png)
+}
+
+/// Cumulative closed PnL per coin
+pub fn create_pnl_chart(
+    fills: &[CustomUserFills],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_pnl(fills))
+}
+
+/// Same as `create_pnl_chart`, returned as PNG bytes for attachments
+pub fn render_pnl_chart(
+    fills: &[CustomUserFills],
+    options: &ChartOptions,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    render_png(options, |root| draw_pnl(root, options, fills))
+}
+
+/// Candlesticks of one coin with the fills of that coin as buy (up) and sell (down) markers
+pub fn create_candle_chart(
+    candles: &[CustomCandle],
+    fills: &[CustomUserFills],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_candles(candles, fills))
+}
+
+/// Position size per coin after each fill
+pub fn create_position_chart(
+    fills: &[CustomUserFills],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_positions(fills))
+}
+
+/// Distance below the running equity peak, e.g. from `PnlReport::equity_curve`
+pub fn create_drawdown_chart(
+    equity_curve: &[EquityPoint],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_drawdown(equity_curve))
+}
+
+/// Cumulative fees in the quote currency, split into maker and taker
+pub fn create_fee_chart(
+    fills: &[CustomUserFills],
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_fees(fills))
+}
+
+/// Cumulative bid and ask size by price of one book snapshot
+pub fn create_depth_chart(
+    book: &CustomL2Book,
+    path: impl AsRef<Path>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>> {
+    render!(path, options, draw_depth(book))
+}
+
+fn draw_candles<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    candles: &[CustomCandle],
+    fills: &[CustomUserFills],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (Some(first), Some(last)) = (candles.first(), candles.last()) else {
+        return Err("No candles to draw".into());
+    };
+    let theme = &options.theme;
+    let (start, end) = padded_time_range(first.time_open as i64, last.time_close as i64);
+    let fills: Vec<&CustomUserFills> = fills
+        .iter()
+        .filter(|fill| fill.coin == first.coin)
+        .filter(|fill| (first.time_open as i64..=last.time_close as i64).contains(&fill.timestamp))
+        .collect();
+
+    let (low, high) = candles
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), candle| {
+            (low.min(candle.low), high.max(candle.high))
+        });
+    let (y_min, y_max) = padded_range(low, high);
+
+    root.fill(&theme.background)?;
+    let title = format!("{} {}", first.coin, first.interval);
+    let mut chart = build_chart(root, options, options.caption(&title))
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+    configure_time_mesh(&mut chart, options, start, end, "Price")?;
+
+    // Bodies narrower than the candle spacing so neighbors do not touch
+    let width = ((options.width as f64 * 0.8) / candles.len() as f64).clamp(1.0, 15.0) as u32;
+    chart.draw_series(candles.iter().map(|candle| {
+        CandleStick::new(
+            to_local(candle.time_open as i64),
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            theme.up.filled(),
+            theme.down.filled(),
+            width,
+        )
+    }))?;
+
+    chart
+        .draw_series(fills.iter().filter(|fill| fill.side == "B").map(|fill| {
+            EmptyElement::at((to_local(fill.timestamp), fill.price))
+                + Polygon::new(vec![(0, -6), (-5, 3), (5, 3)], theme.up.mix(0.9).filled())
+        }))?
+        .label("Buy")
+        .legend({
+            let color = theme.up;
+            move |(x, y)| Rectangle::new([(x, y - 4), (x + 8, y + 4)], color.filled())
+        });
+    chart
+        .draw_series(fills.iter().filter(|fill| fill.side != "B").map(|fill| {
+            EmptyElement::at((to_local(fill.timestamp), fill.price))
+                + Polygon::new(
+                    vec![(0, 6), (-5, -3), (5, -3)],
+                    theme.down.mix(0.9).filled(),
+                )
+        }))?
+        .label("Sell")
+        .legend({
+            let color = theme.down;
+            move |(x, y)| Rectangle::new([(x, y - 4), (x + 8, y + 4)], color.filled())
+        });
+    draw_legend(&mut chart, options)?;
+
+    Ok(())
+}
+
+fn draw_pnl<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    fills: &[CustomUserFills],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut sorted: Vec<&CustomUserFills> = fills.iter().collect();
+    sorted.sort_by_key(|fill| (fill.timestamp, fill.tid));
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return Err("No fills to draw".into());
+    };
+
+    let mut pnl: BTreeMap<&str, Vec<(DateTime<Local>, f64)>> = BTreeMap::new();
+    for fill in &sorted {
+        let points = pnl.entry(&fill.coin).or_default();
+        let before = points.last().map_or(0.0, |&(_, total)| total);
+        points.push((to_local(fill.timestamp), before + fill.closed_pnl));
+    }
+
+    let (start, end) = padded_time_range(first.timestamp, last.timestamp);
+    let (low, high) = value_range(pnl.values().flatten().map(|&(_, total)| total));
+    let (y_min, y_max) = padded_range(low.min(0.0), high.max(0.0));
+
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let mut chart = build_chart(root, options, options.caption("Cumulative PnL"))
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+    configure_time_mesh(&mut chart, options, start, end, "PnL")?;
+
+    for (i, (coin, points)) in pnl.iter().enumerate() {
+        let color = theme.color(i);
+        chart
+            .draw_series(LineSeries::new(points.iter().copied(), &color))?
+            .label(*coin)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    draw_legend(&mut chart, options)?;
+
+    Ok(())
+}
+
+fn draw_positions<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    fills: &[CustomUserFills],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut sorted: Vec<&CustomUserFills> = fills.iter().collect();
+    sorted.sort_by_key(|fill| (fill.timestamp, fill.tid));
+
+    // Steps: the size holds until the next fill changes it. Imported fills may lack
+    // start_position, so only the first fill of a coin seeds the running size.
+    let mut positions: BTreeMap<&str, Vec<(DateTime<Local>, f64)>> = BTreeMap::new();
+    for fill in &sorted {
+        let signed_size = if fill.side == "B" {
+            fill.size
+        } else {
+            -fill.size
+        };
+        let time = to_local(fill.timestamp);
+        let steps = positions.entry(&fill.coin).or_default();
+        let before = steps.last().map_or(fill.start_position, |&(_, size)| size);
+        steps.push((time, before));
+        steps.push((time, before + signed_size));
+    }
+
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return Err("No fills to draw".into());
+    };
+    let (start, end) = padded_time_range(first.timestamp, last.timestamp);
+    let (low, high) = value_range(positions.values().flatten().map(|&(_, size)| size));
+    let (y_min, y_max) = padded_range(low.min(0.0), high.max(0.0));
+
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let mut chart = build_chart(root, options, options.caption("Position size"))
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+    configure_time_mesh(&mut chart, options, start, end, "Size")?;
+
+    for (i, (coin, steps)) in positions.iter().enumerate() {
+        let color = theme.color(i);
+        chart
+            .draw_series(LineSeries::new(steps.iter().copied(), &color))?
+            .label(*coin)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    draw_legend(&mut chart, options)?;
+
+    Ok(())
+}
+
+fn draw_drawdown<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    equity_curve: &[EquityPoint],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let (Some(first), Some(last)) = (equity_curve.first(), equity_curve.last()) else {
+        return Err("No equity curve to draw".into());
+    };
+
+    let mut peak = f64::NEG_INFINITY;
+    let drawdowns: Vec<(DateTime<Local>, f64)> = equity_curve
+        .iter()
+        .map(|point| {
+            peak = peak.max(point.equity);
+            (to_local(point.timestamp), point.equity - peak)
+        })
+        .collect();
+
+    let (start, end) = padded_time_range(first.timestamp, last.timestamp);
+    let (low, _) = value_range(drawdowns.iter().map(|&(_, drawdown)| drawdown));
+    let (y_min, y_max) = padded_range(low.min(0.0), 0.0);
+
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let mut chart = build_chart(root, options, options.caption("Drawdown"))
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+    configure_time_mesh(&mut chart, options, start, end, "Drawdown")?;
+
+    chart.draw_series(
+        AreaSeries::new(drawdowns, 0.0, theme.down.mix(0.3)).border_style(theme.down),
+    )?;
+
+    Ok(())
+}
+
+fn draw_fees<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    fills: &[CustomUserFills],
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let mut sorted: Vec<&CustomUserFills> = fills.iter().collect();
+    sorted.sort_by_key(|fill| (fill.timestamp, fill.tid));
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return Err("No fills to draw".into());
+    };
+
+    let (mut maker, mut taker) = (0.0, 0.0);
+    let mut series: [Vec<(DateTime<Local>, f64)>; 3] = Default::default();
+    for fill in &sorted {
+        if fill.crossed {
+            taker += fee_in_quote(fill);
+        } else {
+            maker += fee_in_quote(fill);
+        }
+        let time = to_local(fill.timestamp);
+        series[0].push((time, maker + taker));
+        series[1].push((time, maker));
+        series[2].push((time, taker));
+    }
+
+    let (start, end) = padded_time_range(first.timestamp, last.timestamp);
+    // Maker rebates make the cumulative fee negative
+    let (low, high) = value_range(series.iter().flatten().map(|&(_, fee)| fee));
+    let (y_min, y_max) = padded_range(low.min(0.0), high.max(0.0));
+
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let mut chart = build_chart(root, options, options.caption("Cumulative fees"))
+        .build_cartesian_2d(start..end, y_min..y_max)?;
+    configure_time_mesh(&mut chart, options, start, end, "Fees")?;
+
+    for (i, (label, points)) in ["Total", "Maker", "Taker"].iter().zip(series).enumerate() {
+        let color = theme.color(i);
+        chart
+            .draw_series(LineSeries::new(points, &color))?
+            .label(*label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    draw_legend(&mut chart, options)?;
+
+    Ok(())
+}
+
+fn draw_depth<DB: DrawingBackend>(
+    root: &DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    book: &CustomL2Book,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    // Levels are sorted from the top of the book outwards
+    let cumulative = |levels: &[CustomLevel]| {
+        let mut total = 0.0;
+        levels
+            .iter()
+            .map(|level| {
+                total += level.size;
+                (level.price, total)
+            })
+            .collect::<Vec<_>>()
+    };
+    let bids = cumulative(&book.bid_levels);
+    let asks = cumulative(&book.ask_levels);
+    if bids.is_empty() && asks.is_empty() {
+        return Err("No book levels to draw".into());
+    }
+
+    let (low, high) = value_range(bids.iter().chain(&asks).map(|&(price, _)| price));
+    let (x_min, x_max) = padded_range(low, high);
+    let (_, max_size) = value_range(bids.iter().chain(&asks).map(|&(_, size)| size));
+    let (_, y_max) = padded_range(0.0, max_size);
+
+    let theme = &options.theme;
+    root.fill(&theme.background)?;
+    let title = format!("{} depth", book.coin);
+    let mut chart = build_chart(root, options, options.caption(&title))
+        .build_cartesian_2d(x_min..x_max, 0.0..y_max)?;
+    chart
+        .configure_mesh()
+        .x_desc("Price")
+        .y_desc("Cumulative size")
+        .light_line_style(theme.grid.mix(0.4))
+        .bold_line_style(theme.grid)
+        .label_style(("sans-serif", 15).into_font().color(&theme.foreground))
+        .axis_style(theme.foreground)
+        .draw()?;
+
+    // Staircase from the mid outwards
+    let steps = |levels: &[(f64, f64)]| {
+        let mut points = Vec::with_capacity(levels.len() * 2);
+        let mut before = 0.0;
+        for &(price, size) in levels {
+            points.push((price, before));
+            points.push((price, size));
+            before = size;
+        }
+        points
+    };
+    chart
+        .draw_series(AreaSeries::new(steps(&bids), 0.0, theme.up.mix(0.3)).border_style(theme.up))?
+        .label("Bids")
+        .legend({
+            let color = theme.up;
+            move |(x, y)| Rectangle::new([(x, y - 4), (x + 8, y + 4)], color.filled())
+        });
+    chart
+        .draw_series(
+            AreaSeries::new(steps(&asks), 0.0, theme.down.mix(0.3)).border_style(theme.down),
+        )?
+        .label("Asks")
+        .legend({
+            let color = theme.down;
+            move |(x, y)| Rectangle::new([(x, y - 4), (x + 8, y + 4)], color.filled())
+        });
+    draw_legend(&mut chart, options)?;
+
+    Ok(())
+}
+
+fn build_chart<'a, 'b, DB: DrawingBackend>(
+    root: &'a DrawingArea<DB, Shift>,
+    options: &ChartOptions,
+    caption: &str,
+) -> ChartBuilder<'a, 'b, DB> {
+    let mut builder = ChartBuilder::on(root);
+    builder
+        .caption(
+            caption,
+            ("sans-serif", 20)
+                .into_font()
+                .color(&options.theme.foreground),
+        )
+        .margin(20)
+        .x_label_area_size(40)
+        .y_label_area_size(70);
+    builder
+}
+
+fn configure_time_mesh<DB: DrawingBackend>(
+    chart: &mut ChartContext<'_, DB, Cartesian2d<RangedDateTime<DateTime<Local>>, RangedCoordf64>>,
+    options: &ChartOptions,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    y_desc: &str,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    let duration = end.signed_duration_since(start);
+    let date_format = if duration.num_days() == 0 {
+        "%H:%M"
+    } else if duration.num_days() < 30 {
+        "%Y-%m-%d %H:%M"
+    } else {
+        "%Y-%m-%d"
+    };
+
+    let theme = &options.theme;
+    chart
+        .configure_mesh()
+        .x_desc("Date")
+        .y_desc(y_desc)
+        .x_label_formatter(&|date| date.format(date_format).to_string())
+        .light_line_style(theme.grid.mix(0.4))
+        .bold_line_style(theme.grid)
+        .label_style(("sans-serif", 15).into_font().color(&theme.foreground))
+        .axis_style(theme.foreground)
+        .draw()?;
+    Ok(())
+}
+
+fn draw_legend<'a, DB: DrawingBackend + 'a, CT: CoordTranslate>(
+    chart: &mut ChartContext<'a, DB, CT>,
+    options: &ChartOptions,
+) -> Result<(), Box<dyn Error>>
+where
+    DB::ErrorType: 'static,
+{
+    chart
+        .configure_series_labels()
+        .background_style(options.theme.background.mix(0.8))
+        .border_style(options.theme.foreground)
+        .label_font(
+            ("sans-serif", 15)
+                .into_font()
+                .color(&options.theme.foreground),
+        )
+        .draw()?;
+    Ok(())
+}
+
+fn to_local(timestamp: i64) -> DateTime<Local> {
+    Local
+        .timestamp_millis_opt(timestamp)
+        .single()
+        .unwrap_or_default()
+}
+
+fn value_range(values: impl Iterator<Item = f64>) -> (f64, f64) {
+    values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), value| {
+        (low.min(value), high.max(value))
+    })
+}
+
+// Like padded_range for the time axis, a single point gets a minute on both sides
+fn padded_time_range(start: i64, end: i64) -> (DateTime<Local>, DateTime<Local>) {
+    let margin = if end > start {
+        (end - start) / 20
+    } else {
+        60 * 1000
+    };
+    (to_local(start - margin), to_local(end + margin))
+}
+
+// 5% margin on both sides, and a non-empty range for flat series
+fn padded_range(low: f64, high: f64) -> (f64, f64) {
+    let margin = if high > low {
+        (high - low) * 0.05
+    } else {
+        low.abs().max(1.0) * 0.05
+    };
+    (low - margin, high + margin)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fill(timestamp: i64, side: &str, fee: f64) -> CustomUserFills {
+        CustomUserFills {
+            closed_pnl: 0.0,
+            coin: "BTC".to_string(),
+            crossed: fee > 0.0,
+            dir: "Open Long".to_string(),
+            hash: format!("0x{:x}", timestamp),
+            order_id: timestamp,
+            price: 100.0,
+            side: side.to_string(),
+            start_position: 0.0,
+            size: 1.0,
+            timestamp,
+            fee,
+            tid: timestamp,
+        }
+    }
+
+    fn candle(time_open: u64) -> CustomCandle {
+        CustomCandle {
+            coin: "BTC".to_string(),
+            interval: "1m".to_string(),
+            open: 100.0,
+            high: 100.0,
+            low: 100.0,
+            close: 100.0,
+            volume: 0.0,
+            num_trades: 0,
+            time_close: time_open + 59_999,
+            time_open,
+        }
+    }
+
+    fn level(price: f64, size: f64) -> CustomLevel {
+        CustomLevel {
+            price,
+            size,
+            num_orders: 1,
+        }
+    }
+
+    fn svg_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("chart_rs_{}_{}.svg", name, std::process::id()))
+    }
+
+    fn render(name: &str, draw: impl FnOnce(&Path) -> Result<(), Box<dyn Error>>) {
+        let path = svg_path(name);
+        let result = draw(&path);
+        let _ = std::fs::remove_file(&path);
+        if let Err(e) = result {
+            panic!("{} chart failed: {}", name, e);
+        }
+    }
+
+    #[test]
+    fn empty_palette_falls_back_to_the_default() {
+        let mut theme = ChartTheme::light();
+        theme.palette.clear();
+        assert_eq!(theme.color(0), default_palette()[0]);
+        assert_eq!(
+            theme.color(default_palette().len() + 1),
+            default_palette()[1]
+        );
+
+        theme.palette = vec![RED];
+        assert_eq!(theme.color(3), RED);
+    }
+
+    #[test]
+    fn padded_range_never_collapses() {
+        assert_eq!(padded_range(0.0, 10.0), (-0.5, 10.5));
+        assert_eq!(padded_range(0.0, 0.0), (-0.05, 0.05));
+        assert_eq!(padded_range(200.0, 200.0), (190.0, 210.0));
+        assert_eq!(padded_range(-200.0, -200.0), (-210.0, -190.0));
+    }
+
+    #[test]
+    fn padded_time_range_never_collapses() {
+        let (start, end) = padded_time_range(1_000_000, 3_000_000);
+        assert_eq!(
+            (start.timestamp_millis(), end.timestamp_millis()),
+            (900_000, 3_100_000)
+        );
+        let (start, end) = padded_time_range(1_000_000, 1_000_000);
+        assert_eq!(
+            (start.timestamp_millis(), end.timestamp_millis()),
+            (940_000, 1_060_000)
+        );
+    }
+
+    #[test]
+    fn value_range_of_values() {
+        assert_eq!(value_range([3.0, -1.0, 2.0].into_iter()), (-1.0, 3.0));
+        assert_eq!(value_range([5.0].into_iter()), (5.0, 5.0));
+    }
+
+    #[test]
+    fn single_point_inputs_render() {
+        let options = ChartOptions::default().size(320, 240);
+        let fills = [fill(1_700_000_000_000, "B", 0.1)];
+        render("candle", |path| {
+            create_candle_chart(&[candle(1_700_000_000_000)], &fills, path, &options)
+        });
+        render("position", |path| {
+            create_position_chart(&fills, path, &options)
+        });
+        render("fees", |path| create_fee_chart(&fills, path, &options));
+        render("drawdown", |path| {
+            let curve = [EquityPoint {
+                timestamp: 1_700_000_000_000,
+                equity: 0.0,
+            }];
+            create_drawdown_chart(&curve, path, &options)
+        });
+        render("depth", |path| {
+            let book = CustomL2Book {
+                coin: "BTC".to_string(),
+                bid_levels: vec![level(100.0, 0.0)],
+                ask_levels: Vec::new(),
+                timestamp: 0,
+            };
+            create_depth_chart(&book, path, &options)
+        });
+    }
+
+    #[test]
+    fn pnl_chart_renders_to_png_bytes() {
+        let options = ChartOptions::default().size(320, 240);
+        let mut fills = vec![
+            fill(1_700_000_000_000, "B", 0.1),
+            fill(1_700_000_060_000, "A", 0.1),
+        ];
+        fills[1].closed_pnl = 5.0;
+        let png = render_pnl_chart(&fills, &options).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        assert!(render_pnl_chart(&[], &options).is_err());
+    }
+
+    #[test]
+    fn empty_inputs_are_errors() {
+        let options = ChartOptions::default();
+        let path = svg_path("empty");
+        assert!(create_candle_chart(&[], &[], &path, &options).is_err());
+        assert!(create_pnl_chart(&[], &path, &options).is_err());
+        assert!(create_position_chart(&[], &path, &options).is_err());
+        assert!(create_fee_chart(&[], &path, &options).is_err());
+        assert!(create_drawdown_chart(&[], &path, &options).is_err());
+        let book = CustomL2Book {
+            coin: "BTC".to_string(),
+            bid_levels: Vec::new(),
+            ask_levels: Vec::new(),
+            timestamp: 0,
+        };
+        assert!(create_depth_chart(&book, &path, &options).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
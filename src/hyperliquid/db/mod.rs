@@ -0,0 +1,83 @@
+pub mod postgres;
+pub mod sqlite;
+
+use crate::hyperliquid::model::CustomUserFills;
+use crate::hyperliquid::order_audit::{OrderAuditQuery, OrderRequestRecord, OrderStatusTransition};
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::H160;
+use postgres::PostgresStorage;
+use sqlite::SqliteStorage;
+use std::sync::Arc;
+
+/// Persistence backend for fills and bot history
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Applies pending schema migrations and returns how many were applied
+    async fn migrate(&self) -> Result<usize>;
+
+    /// Upserts the fills in one transaction, keyed on (user_address, hash, order_id, timestamp,
+    /// price, size, start_position) so fills with and without a tid match
+    async fn save_fills(&self, fills: &[CustomUserFills], user: H160) -> Result<()>;
+
+    async fn load_fills(&self, user: H160) -> Result<Vec<CustomUserFills>>;
+
+    /// Fills with `start_time <= timestamp <= end_time`, in milliseconds
+    async fn load_fills_with_time_filter(
+        &self,
+        user: H160,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<CustomUserFills>>;
+
+    /// Stores one exchange call in `order_requests`, records its status transitions and
+    /// keeps the order's row in `orders` up to date, all in one transaction
+    async fn save_order_request(&self, record: &OrderRequestRecord, user: H160) -> Result<()>;
+
+    /// Order requests by `sent_at`, oldest first
+    async fn load_order_requests(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderRequestRecord>>;
+
+    /// Status transitions by `timestamp`, oldest first
+    async fn load_order_transitions(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderStatusTransition>>;
+}
+
+/// Opens the backend selected by the scheme of `database_url`: `sqlite://path/to/file.db`
+/// (or `sqlite::memory:`) for an embedded SQLite file, anything else is passed to Postgres.
+pub async fn connect_storage(database_url: &str) -> Result<Arc<dyn Storage>> {
+    if database_url.starts_with("sqlite:") {
+        Ok(Arc::new(SqliteStorage::open(database_url)?))
+    } else {
+        Ok(Arc::new(PostgresStorage::connect(database_url).await?))
+    }
+}
+
+pub async fn save_fills_to_db(
+    storage: &dyn Storage,
+    fills: &[CustomUserFills],
+    user: H160,
+) -> Result<()> {
+    storage.save_fills(fills, user).await
+}
+
+pub async fn load_fills_from_db(storage: &dyn Storage, user: H160) -> Result<Vec<CustomUserFills>> {
+    storage.load_fills(user).await
+}
+
+pub async fn load_fills_from_db_with_time_filter(
+    storage: &dyn Storage,
+    user: H160,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<CustomUserFills>> {
+    storage
+        .load_fills_with_time_filter(user, start_time, end_time)
+        .await
+}
@@ -0,0 +1,441 @@
+use crate::hyperliquid::db::Storage;
+use crate::hyperliquid::model::CustomUserFills;
+use crate::hyperliquid::order_audit::{OrderAuditQuery, OrderRequestRecord, OrderStatusTransition};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::types::H160;
+use log::{error, info};
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
+use tokio_postgres::{Client, NoTls, Row};
+
+// Arbitrary key that serializes bots migrating the same database at startup
+const MIGRATION_LOCK_KEY: i64 = 0x6d6f6d6f;
+
+/// Schema migrations embedded at build time, applied in version order.
+/// Applied migrations must never be edited, add a new one instead.
+const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (
+        1,
+        "create_user_fills",
+        include_str!("../../../migrations/postgres/0001_create_user_fills.sql"),
+    ),
+    (
+        2,
+        "user_fills_unique_key",
+        include_str!("../../../migrations/postgres/0002_user_fills_unique_key.sql"),
+    ),
+    (
+        3,
+        "user_fills_indexes",
+        include_str!("../../../migrations/postgres/0003_user_fills_indexes.sql"),
+    ),
+    (
+        4,
+        "create_orders",
+        include_str!("../../../migrations/postgres/0004_create_orders.sql"),
+    ),
+    (
+        5,
+        "create_funding_payments",
+        include_str!("../../../migrations/postgres/0005_create_funding_payments.sql"),
+    ),
+    (
+        6,
+        "create_position_snapshots",
+        include_str!("../../../migrations/postgres/0006_create_position_snapshots.sql"),
+    ),
+    (
+        7,
+        "create_pnl_snapshots",
+        include_str!("../../../migrations/postgres/0007_create_pnl_snapshots.sql"),
+    ),
+    (
+        8,
+        "create_order_requests",
+        include_str!("../../../migrations/postgres/0008_create_order_requests.sql"),
+    ),
+];
+
+const SELECT_FILLS: &str = "SELECT closed_pnl, coin, crossed, dir, hash, order_id, price, side, start_position, size, timestamp, fee, tid FROM user_fills";
+const SELECT_ORDER_REQUESTS: &str = "SELECT action, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, response, sent_at, latency_ms FROM order_requests";
+const SELECT_ORDER_TRANSITIONS: &str =
+    "SELECT order_id, cloid, status, message, timestamp FROM order_status_transitions";
+
+/// Postgres backend. The connection is re-established on the next call after it dropped.
+pub struct PostgresStorage {
+    database_url: String,
+    client: Mutex<Option<Client>>,
+}
+
+impl PostgresStorage {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let client = connect_client(database_url).await?;
+        Ok(Self {
+            database_url: database_url.to_string(),
+            client: Mutex::new(Some(client)),
+        })
+    }
+
+    async fn client(&self) -> Result<MappedMutexGuard<'_, Client>> {
+        let mut client = self.client.lock().await;
+        if client.as_ref().is_none_or(|client| client.is_closed()) {
+            *client = None;
+            *client = Some(connect_client(&self.database_url).await?);
+            info!("Reconnected to database");
+        }
+        MutexGuard::try_map(client, |client| client.as_mut())
+            .map_err(|_| anyhow!("Database client is not connected"))
+    }
+}
+
+async fn connect_client(database_url: &str) -> Result<Client> {
+    let (client, connection) = tokio_postgres::connect(database_url, NoTls)
+        .await
+        .context("Failed to connect to database")?;
+    tokio::spawn(async move {
+        if let Err(e) = connection.await {
+            error!("Connection error: {}", e);
+        }
+    });
+    Ok(client)
+}
+
+#[async_trait]
+impl Storage for PostgresStorage {
+    async fn migrate(&self) -> Result<usize> {
+        let mut client = self.client().await?;
+        run_migrations(&mut client).await
+    }
+
+    async fn save_fills(&self, fills: &[CustomUserFills], user: H160) -> Result<()> {
+        let mut client = self.client().await?;
+        let transaction = client.transaction().await?;
+        let statement = transaction.prepare(
+            "INSERT INTO user_fills (user_address, closed_pnl, coin, crossed, dir, hash, order_id, price, side, start_position, size, timestamp, fee, tid)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+             ON CONFLICT (user_address, hash, order_id, timestamp, price, size, start_position) DO UPDATE SET
+                 closed_pnl = EXCLUDED.closed_pnl, coin = EXCLUDED.coin, crossed = EXCLUDED.crossed,
+                 dir = EXCLUDED.dir, side = EXCLUDED.side, fee = EXCLUDED.fee,
+                 tid = GREATEST(user_fills.tid, EXCLUDED.tid)",
+        ).await?;
+
+        let user_address = user.to_string();
+        for fill in fills {
+            transaction
+                .execute(
+                    &statement,
+                    &[
+                        &user_address,
+                        &fill.closed_pnl,
+                        &fill.coin,
+                        &fill.crossed,
+                        &fill.dir,
+                        &fill.hash,
+                        &fill.order_id,
+                        &fill.price,
+                        &fill.side,
+                        &fill.start_position,
+                        &fill.size,
+                        &fill.timestamp,
+                        &fill.fee,
+                        &fill.tid,
+                    ],
+                )
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn load_fills(&self, user: H160) -> Result<Vec<CustomUserFills>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                &format!(
+                    "{} WHERE user_address = $1 ORDER BY timestamp, id",
+                    SELECT_FILLS
+                ),
+                &[&user.to_string()],
+            )
+            .await?;
+        Ok(rows.iter().map(fill_from_row).collect())
+    }
+
+    async fn load_fills_with_time_filter(
+        &self,
+        user: H160,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<CustomUserFills>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                &format!(
+                    "{} WHERE user_address = $1 AND timestamp BETWEEN $2 AND $3 ORDER BY timestamp, id",
+                    SELECT_FILLS
+                ),
+                &[&user.to_string(), &start_time, &end_time],
+            )
+            .await?;
+        Ok(rows.iter().map(fill_from_row).collect())
+    }
+
+    async fn save_order_request(&self, record: &OrderRequestRecord, user: H160) -> Result<()> {
+        let mut client = self.client().await?;
+        let transaction = client.transaction().await?;
+        let user_address = user.to_string();
+        let responded_at = record.sent_at + record.latency_ms;
+
+        transaction
+            .execute(
+                "INSERT INTO order_requests (user_address, action, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, response, sent_at, latency_ms)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+                &[
+                    &user_address,
+                    &record.action,
+                    &record.coin,
+                    &record.order_id,
+                    &record.cloid,
+                    &record.side,
+                    &record.price,
+                    &record.size,
+                    &record.order_type,
+                    &record.reduce_only,
+                    &record.status,
+                    &record.response,
+                    &record.sent_at,
+                    &record.latency_ms,
+                ],
+            )
+            .await?;
+
+        let statement = transaction
+            .prepare(
+                "INSERT INTO order_status_transitions (user_address, order_id, cloid, status, message, timestamp)
+                 VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .await?;
+        for (status, timestamp) in [
+            (record.sent_status(), record.sent_at),
+            (record.status.as_str(), responded_at),
+        ] {
+            transaction
+                .execute(
+                    &statement,
+                    &[
+                        &user_address,
+                        &record.order_id,
+                        &record.cloid,
+                        &status,
+                        &record.action,
+                        &timestamp,
+                    ],
+                )
+                .await?;
+        }
+
+        if let (Some(side), Some(size), Some(order_type)) =
+            (&record.side, record.size, &record.order_type)
+        {
+            transaction
+                .execute(
+                    "INSERT INTO orders (user_address, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, created_at, updated_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+                     ON CONFLICT (user_address, order_id) DO UPDATE SET
+                         status = EXCLUDED.status, updated_at = EXCLUDED.updated_at",
+                    &[
+                        &user_address,
+                        &record.coin,
+                        &record.order_id,
+                        &record.cloid,
+                        side,
+                        &record.price,
+                        &size,
+                        order_type,
+                        &record.reduce_only,
+                        &record.status,
+                        &record.sent_at,
+                        &responded_at,
+                    ],
+                )
+                .await?;
+        } else {
+            transaction
+                .execute(
+                    "UPDATE orders SET status = $1, updated_at = $2
+                     WHERE user_address = $3 AND (order_id = $4 OR cloid = $5)",
+                    &[
+                        &record.status,
+                        &responded_at,
+                        &user_address,
+                        &record.order_id,
+                        &record.cloid,
+                    ],
+                )
+                .await?;
+        }
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn load_order_requests(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderRequestRecord>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                &format!(
+                    "{} WHERE user_address = $1 AND ($2::BIGINT IS NULL OR sent_at >= $2)
+                     AND ($3::BIGINT IS NULL OR sent_at <= $3) AND ($4::VARCHAR IS NULL OR cloid = $4)
+                     ORDER BY sent_at, id",
+                    SELECT_ORDER_REQUESTS
+                ),
+                &[
+                    &user.to_string(),
+                    &query.start_time,
+                    &query.end_time,
+                    &query.cloid,
+                ],
+            )
+            .await?;
+        Ok(rows.iter().map(order_request_from_row).collect())
+    }
+
+    async fn load_order_transitions(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderStatusTransition>> {
+        let rows = self
+            .client()
+            .await?
+            .query(
+                &format!(
+                    "{} WHERE user_address = $1 AND ($2::BIGINT IS NULL OR timestamp >= $2)
+                     AND ($3::BIGINT IS NULL OR timestamp <= $3) AND ($4::VARCHAR IS NULL OR cloid = $4)
+                     ORDER BY timestamp, id",
+                    SELECT_ORDER_TRANSITIONS
+                ),
+                &[
+                    &user.to_string(),
+                    &query.start_time,
+                    &query.end_time,
+                    &query.cloid,
+                ],
+            )
+            .await?;
+        Ok(rows
+            .iter()
+            .map(|row| OrderStatusTransition {
+                order_id: row.get("order_id"),
+                cloid: row.get("cloid"),
+                status: row.get("status"),
+                message: row.get("message"),
+                timestamp: row.get("timestamp"),
+            })
+            .collect())
+    }
+}
+
+fn order_request_from_row(row: &Row) -> OrderRequestRecord {
+    OrderRequestRecord {
+        action: row.get("action"),
+        coin: row.get("coin"),
+        order_id: row.get("order_id"),
+        cloid: row.get("cloid"),
+        side: row.get("side"),
+        price: row.get("price"),
+        size: row.get("size"),
+        order_type: row.get("order_type"),
+        reduce_only: row.get("reduce_only"),
+        status: row.get("status"),
+        response: row.get("response"),
+        sent_at: row.get("sent_at"),
+        latency_ms: row.get("latency_ms"),
+    }
+}
+
+fn fill_from_row(row: &Row) -> CustomUserFills {
+    CustomUserFills {
+        closed_pnl: row.get("closed_pnl"),
+        coin: row.get("coin"),
+        crossed: row.get("crossed"),
+        dir: row.get("dir"),
+        hash: row.get("hash"),
+        order_id: row.get("order_id"),
+        price: row.get("price"),
+        side: row.get("side"),
+        start_position: row.get("start_position"),
+        size: row.get("size"),
+        timestamp: row.get("timestamp"),
+        fee: row.get("fee"),
+        tid: row.get("tid"),
+    }
+}
+
+/// Applies every migration that is not recorded in `schema_migrations` yet,
+/// each in its own transaction. Returns the number of applied migrations.
+pub async fn run_migrations(client: &mut Client) -> Result<usize> {
+    // Taken before creating schema_migrations, whose concurrent creation can fail
+    client
+        .execute("SELECT pg_advisory_lock($1)", &[&MIGRATION_LOCK_KEY])
+        .await
+        .context("Failed to acquire migration lock")?;
+    let result = apply_pending(client).await;
+    client
+        .execute("SELECT pg_advisory_unlock($1)", &[&MIGRATION_LOCK_KEY])
+        .await
+        .context("Failed to release migration lock")?;
+    result
+}
+
+async fn apply_pending(client: &mut Client) -> Result<usize> {
+    client
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version BIGINT PRIMARY KEY,
+                name VARCHAR(100) NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+        )
+        .await
+        .context("Failed to create schema_migrations table")?;
+
+    let applied: Vec<i64> = client
+        .query("SELECT version FROM schema_migrations", &[])
+        .await
+        .context("Failed to read applied migrations")?
+        .iter()
+        .map(|row| row.get("version"))
+        .collect();
+
+    let mut count = 0;
+    for (version, name, sql) in MIGRATIONS {
+        if applied.contains(version) {
+            continue;
+        }
+
+        let transaction = client.transaction().await?;
+        transaction
+            .batch_execute(sql)
+            .await
+            .with_context(|| format!("Migration {:04}_{} failed", version, name))?;
+        transaction
+            .execute(
+                "INSERT INTO schema_migrations (version, name) VALUES ($1, $2)",
+                &[version, name],
+            )
+            .await?;
+        transaction.commit().await?;
+
+        info!("Applied migration {:04}_{}", version, name);
+        count += 1;
+    }
+    Ok(count)
+}
@@ -0,0 +1,425 @@
+use crate::hyperliquid::db::Storage;
+use crate::hyperliquid::model::CustomUserFills;
+use crate::hyperliquid::order_audit::{OrderAuditQuery, OrderRequestRecord, OrderStatusTransition};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use ethers::types::H160;
+use log::info;
+use rusqlite::{params, Connection, Row};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Same schema as the Postgres migrations in SQLite dialect, versions must stay in sync.
+const MIGRATIONS: &[(i64, &str, &str)] = &[
+    (
+        1,
+        "create_user_fills",
+        include_str!("../../../migrations/sqlite/0001_create_user_fills.sql"),
+    ),
+    (
+        2,
+        "user_fills_unique_key",
+        include_str!("../../../migrations/sqlite/0002_user_fills_unique_key.sql"),
+    ),
+    (
+        3,
+        "user_fills_indexes",
+        include_str!("../../../migrations/sqlite/0003_user_fills_indexes.sql"),
+    ),
+    (
+        4,
+        "create_orders",
+        include_str!("../../../migrations/sqlite/0004_create_orders.sql"),
+    ),
+    (
+        5,
+        "create_funding_payments",
+        include_str!("../../../migrations/sqlite/0005_create_funding_payments.sql"),
+    ),
+    (
+        6,
+        "create_position_snapshots",
+        include_str!("../../../migrations/sqlite/0006_create_position_snapshots.sql"),
+    ),
+    (
+        7,
+        "create_pnl_snapshots",
+        include_str!("../../../migrations/sqlite/0007_create_pnl_snapshots.sql"),
+    ),
+    (
+        8,
+        "create_order_requests",
+        include_str!("../../../migrations/sqlite/0008_create_order_requests.sql"),
+    ),
+];
+
+const SELECT_FILLS: &str = "SELECT closed_pnl, coin, crossed, dir, hash, order_id, price, side, start_position, size, timestamp, fee, tid FROM user_fills";
+const SELECT_ORDER_REQUESTS: &str = "SELECT action, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, response, sent_at, latency_ms FROM order_requests";
+const SELECT_ORDER_TRANSITIONS: &str =
+    "SELECT order_id, cloid, status, message, timestamp FROM order_status_transitions";
+
+// Waits for the other bots' transactions when several share one file
+const BUSY_TIMEOUT_SECS: u64 = 5;
+
+/// Embedded SQLite backend. rusqlite is blocking, so every call runs on the blocking pool.
+pub struct SqliteStorage {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStorage {
+    /// Opens `sqlite://path/to/file.db`, `sqlite:path/to/file.db` or `sqlite::memory:`
+    pub fn open(database_url: &str) -> Result<Self> {
+        let path = database_url
+            .strip_prefix("sqlite://")
+            .or_else(|| database_url.strip_prefix("sqlite:"))
+            .ok_or_else(|| anyhow!("Not a sqlite url: {}", database_url))?;
+
+        let connection = if path == ":memory:" {
+            Connection::open_in_memory()
+        } else {
+            Connection::open(path)
+        }
+        .with_context(|| format!("Failed to open sqlite database {}", path))?;
+
+        connection.busy_timeout(Duration::from_secs(BUSY_TIMEOUT_SECS))?;
+        connection
+            .pragma_update(None, "journal_mode", "WAL")
+            .context("Failed to enable WAL journal")?;
+
+        Ok(Self {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut connection = connection
+                .lock()
+                .map_err(|_| anyhow!("Sqlite connection lock poisoned"))?;
+            f(&mut connection)
+        })
+        .await?
+    }
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn migrate(&self) -> Result<usize> {
+        self.run(run_migrations).await
+    }
+
+    async fn save_fills(&self, fills: &[CustomUserFills], user: H160) -> Result<()> {
+        let fills = fills.to_vec();
+        let user_address = user.to_string();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            {
+                let mut statement = transaction.prepare(
+                    "INSERT INTO user_fills (user_address, closed_pnl, coin, crossed, dir, hash, order_id, price, side, start_position, size, timestamp, fee, tid)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+                     ON CONFLICT (user_address, hash, order_id, timestamp, price, size, start_position) DO UPDATE SET
+                         closed_pnl = excluded.closed_pnl, coin = excluded.coin, crossed = excluded.crossed,
+                         dir = excluded.dir, side = excluded.side, fee = excluded.fee,
+                         tid = MAX(user_fills.tid, excluded.tid)",
+                )?;
+                for fill in &fills {
+                    statement.execute(params![
+                        user_address,
+                        fill.closed_pnl,
+                        fill.coin,
+                        fill.crossed,
+                        fill.dir,
+                        fill.hash,
+                        fill.order_id,
+                        fill.price,
+                        fill.side,
+                        fill.start_position,
+                        fill.size,
+                        fill.timestamp,
+                        fill.fee,
+                        fill.tid,
+                    ])?;
+                }
+            }
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_fills(&self, user: H160) -> Result<Vec<CustomUserFills>> {
+        let user_address = user.to_string();
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "{} WHERE user_address = ?1 ORDER BY timestamp, id",
+                SELECT_FILLS
+            ))?;
+            let fills = statement
+                .query_map(params![user_address], fill_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(fills)
+        })
+        .await
+    }
+
+    async fn load_fills_with_time_filter(
+        &self,
+        user: H160,
+        start_time: i64,
+        end_time: i64,
+    ) -> Result<Vec<CustomUserFills>> {
+        let user_address = user.to_string();
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "{} WHERE user_address = ?1 AND timestamp BETWEEN ?2 AND ?3 ORDER BY timestamp, id",
+                SELECT_FILLS
+            ))?;
+            let fills = statement
+                .query_map(params![user_address, start_time, end_time], fill_from_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(fills)
+        })
+        .await
+    }
+
+    async fn save_order_request(&self, record: &OrderRequestRecord, user: H160) -> Result<()> {
+        let record = record.clone();
+        let user_address = user.to_string();
+        self.run(move |connection| {
+            let transaction = connection.transaction()?;
+            let responded_at = record.sent_at + record.latency_ms;
+
+            transaction.execute(
+                "INSERT INTO order_requests (user_address, action, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, response, sent_at, latency_ms)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                params![
+                    user_address,
+                    record.action,
+                    record.coin,
+                    record.order_id,
+                    record.cloid,
+                    record.side,
+                    record.price,
+                    record.size,
+                    record.order_type,
+                    record.reduce_only,
+                    record.status,
+                    record.response,
+                    record.sent_at,
+                    record.latency_ms,
+                ],
+            )?;
+
+            {
+                let mut statement = transaction.prepare(
+                    "INSERT INTO order_status_transitions (user_address, order_id, cloid, status, message, timestamp)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                )?;
+                for (status, timestamp) in [
+                    (record.sent_status(), record.sent_at),
+                    (record.status.as_str(), responded_at),
+                ] {
+                    statement.execute(params![
+                        user_address,
+                        record.order_id,
+                        record.cloid,
+                        status,
+                        record.action,
+                        timestamp,
+                    ])?;
+                }
+            }
+
+            if let (Some(side), Some(size), Some(order_type)) =
+                (&record.side, record.size, &record.order_type)
+            {
+                transaction.execute(
+                    "INSERT INTO orders (user_address, coin, order_id, cloid, side, price, size, order_type, reduce_only, status, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                     ON CONFLICT (user_address, order_id) DO UPDATE SET
+                         status = excluded.status, updated_at = excluded.updated_at",
+                    params![
+                        user_address,
+                        record.coin,
+                        record.order_id,
+                        record.cloid,
+                        side,
+                        record.price,
+                        size,
+                        order_type,
+                        record.reduce_only,
+                        record.status,
+                        record.sent_at,
+                        responded_at,
+                    ],
+                )?;
+            } else {
+                transaction.execute(
+                    "UPDATE orders SET status = ?1, updated_at = ?2
+                     WHERE user_address = ?3 AND (order_id = ?4 OR cloid = ?5)",
+                    params![
+                        record.status,
+                        responded_at,
+                        user_address,
+                        record.order_id,
+                        record.cloid,
+                    ],
+                )?;
+            }
+
+            transaction.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn load_order_requests(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderRequestRecord>> {
+        let user_address = user.to_string();
+        let query = query.clone();
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "{} WHERE user_address = ?1 AND (?2 IS NULL OR sent_at >= ?2)
+                 AND (?3 IS NULL OR sent_at <= ?3) AND (?4 IS NULL OR cloid = ?4)
+                 ORDER BY sent_at, id",
+                SELECT_ORDER_REQUESTS
+            ))?;
+            let records = statement
+                .query_map(
+                    params![user_address, query.start_time, query.end_time, query.cloid],
+                    order_request_from_row,
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(records)
+        })
+        .await
+    }
+
+    async fn load_order_transitions(
+        &self,
+        user: H160,
+        query: &OrderAuditQuery,
+    ) -> Result<Vec<OrderStatusTransition>> {
+        let user_address = user.to_string();
+        let query = query.clone();
+        self.run(move |connection| {
+            let mut statement = connection.prepare(&format!(
+                "{} WHERE user_address = ?1 AND (?2 IS NULL OR timestamp >= ?2)
+                 AND (?3 IS NULL OR timestamp <= ?3) AND (?4 IS NULL OR cloid = ?4)
+                 ORDER BY timestamp, id",
+                SELECT_ORDER_TRANSITIONS
+            ))?;
+            let transitions = statement
+                .query_map(
+                    params![user_address, query.start_time, query.end_time, query.cloid],
+                    |row| {
+                        Ok(OrderStatusTransition {
+                            order_id: row.get("order_id")?,
+                            cloid: row.get("cloid")?,
+                            status: row.get("status")?,
+                            message: row.get("message")?,
+                            timestamp: row.get("timestamp")?,
+                        })
+                    },
+                )?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(transitions)
+        })
+        .await
+    }
+}
+
+fn order_request_from_row(row: &Row) -> rusqlite::Result<OrderRequestRecord> {
+    Ok(OrderRequestRecord {
+        action: row.get("action")?,
+        coin: row.get("coin")?,
+        order_id: row.get("order_id")?,
+        cloid: row.get("cloid")?,
+        side: row.get("side")?,
+        price: row.get("price")?,
+        size: row.get("size")?,
+        order_type: row.get("order_type")?,
+        reduce_only: row.get("reduce_only")?,
+        status: row.get("status")?,
+        response: row.get("response")?,
+        sent_at: row.get("sent_at")?,
+        latency_ms: row.get("latency_ms")?,
+    })
+}
+
+fn fill_from_row(row: &Row) -> rusqlite::Result<CustomUserFills> {
+    Ok(CustomUserFills {
+        closed_pnl: row.get("closed_pnl")?,
+        coin: row.get("coin")?,
+        crossed: row.get("crossed")?,
+        dir: row.get("dir")?,
+        hash: row.get("hash")?,
+        order_id: row.get("order_id")?,
+        price: row.get("price")?,
+        side: row.get("side")?,
+        start_position: row.get("start_position")?,
+        size: row.get("size")?,
+        timestamp: row.get("timestamp")?,
+        fee: row.get("fee")?,
+        tid: row.get("tid")?,
+    })
+}
+
+/// Applies every migration that is not recorded in `schema_migrations` yet,
+/// each in its own transaction. Returns the number of applied migrations.
+pub fn run_migrations(connection: &mut Connection) -> Result<usize> {
+    connection
+        .execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_migrations (
+                version INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )",
+        )
+        .context("Failed to create schema_migrations table")?;
+
+    let applied: Vec<i64> = connection
+        .prepare("SELECT version FROM schema_migrations")?
+        .query_map([], |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to read applied migrations")?;
+
+    let mut count = 0;
+    for (version, name, sql) in MIGRATIONS {
+        if applied.contains(version) {
+            continue;
+        }
+
+        // IMMEDIATE takes the write lock up front, which serializes bots migrating one file
+        let transaction =
+            connection.transaction_with_behavior(rusqlite::TransactionBehavior::Immediate)?;
+        let already_applied: bool = transaction.query_row(
+            "SELECT EXISTS (SELECT 1 FROM schema_migrations WHERE version = ?1)",
+            params![version],
+            |row| row.get(0),
+        )?;
+        if already_applied {
+            continue;
+        }
+        transaction
+            .execute_batch(sql)
+            .with_context(|| format!("Migration {:04}_{} failed", version, name))?;
+        transaction.execute(
+            "INSERT INTO schema_migrations (version, name) VALUES (?1, ?2)",
+            params![version, name],
+        )?;
+        transaction.commit()?;
+
+        info!("Applied migration {:04}_{}", version, name);
+        count += 1;
+    }
+    Ok(count)
+}
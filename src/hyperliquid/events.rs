@@ -0,0 +1,115 @@
+use crate::hyperliquid::model::{CustomCandle, CustomL2Book, CustomTrade, CustomUserFills};
+use std::collections::HashMap;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+
+/// Item received from the websocket. `seq` is the item's sequence in its `WsData`
+/// buffer and can be passed to the `get_*_since` getters to catch up after a lag.
+#[derive(Debug, Clone)]
+pub enum MarketEvent {
+    AllMids(HashMap<String, String>),
+    Trade { seq: u64, trade: CustomTrade },
+    Candle { seq: u64, candle: CustomCandle },
+    L2Book { seq: u64, l2_book: CustomL2Book },
+    UserFill { seq: u64, fill: CustomUserFills },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventChannel {
+    AllMids,
+    Trades,
+    Candle,
+    L2Book,
+    UserFills,
+}
+
+impl MarketEvent {
+    pub fn channel(&self) -> EventChannel {
+        match self {
+            MarketEvent::AllMids(_) => EventChannel::AllMids,
+            MarketEvent::Trade { .. } => EventChannel::Trades,
+            MarketEvent::Candle { .. } => EventChannel::Candle,
+            MarketEvent::L2Book { .. } => EventChannel::L2Book,
+            MarketEvent::UserFill { .. } => EventChannel::UserFills,
+        }
+    }
+
+    /// `None` for mids, which cover every coin
+    pub fn coin(&self) -> Option<&str> {
+        match self {
+            MarketEvent::AllMids(_) => None,
+            MarketEvent::Trade { trade, .. } => Some(&trade.coin),
+            MarketEvent::Candle { candle, .. } => Some(&candle.coin),
+            MarketEvent::L2Book { l2_book, .. } => Some(&l2_book.coin),
+            MarketEvent::UserFill { fill, .. } => Some(&fill.coin),
+        }
+    }
+
+    pub fn seq(&self) -> Option<u64> {
+        match self {
+            MarketEvent::AllMids(_) => None,
+            MarketEvent::Trade { seq, .. }
+            | MarketEvent::Candle { seq, .. }
+            | MarketEvent::L2Book { seq, .. }
+            | MarketEvent::UserFill { seq, .. } => Some(*seq),
+        }
+    }
+}
+
+/// Selects the events an `EventReceiver` yields, everything by default
+#[derive(Debug, Clone, Default)]
+pub struct EventFilter {
+    pub channel: Option<EventChannel>,
+    pub coin: Option<String>,
+}
+
+impl EventFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn channel(mut self, value: EventChannel) -> Self {
+        self.channel = Some(value);
+        self
+    }
+
+    pub fn coin(mut self, value: String) -> Self {
+        self.coin = Some(value);
+        self
+    }
+
+    pub fn matches(&self, event: &MarketEvent) -> bool {
+        if self
+            .channel
+            .is_some_and(|channel| channel != event.channel())
+        {
+            return false;
+        }
+        match (&self.coin, event.coin()) {
+            (Some(coin), Some(event_coin)) => coin == event_coin,
+            _ => true,
+        }
+    }
+}
+
+pub struct EventReceiver {
+    receiver: Receiver<MarketEvent>,
+    filter: EventFilter,
+}
+
+impl EventReceiver {
+    pub fn new(receiver: Receiver<MarketEvent>, filter: EventFilter) -> Self {
+        Self { receiver, filter }
+    }
+
+    /// Waits for the next matching event. `RecvError::Lagged` means events were
+    /// dropped because the receiver fell behind; use the cursor getters to catch up.
+    pub async fn recv(&mut self) -> Result<MarketEvent, RecvError> {
+        loop {
+            let event = self.receiver.recv().await?;
+            if self.filter.matches(&event) {
+                return Ok(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,492 @@
+use crate::hyperliquid::db::{load_fills_from_db_with_time_filter, save_fills_to_db, Storage};
+use crate::hyperliquid::model::CustomUserFills;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone};
+use ethers::types::H160;
+use log::{info, warn};
+use parquet::data_type::{BoolType, ByteArray, ByteArrayType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const IMPORT_CHUNK_SIZE: usize = 500;
+// Hash of UI export rows, which have none
+const CSV_HASH_PREFIX: &str = "csv:";
+
+// Formats of the time column in Hyperliquid UI exports, in the timezone of the browser
+const UI_TIME_FORMATS: &[&str] = &["%d/%m/%Y - %H:%M:%S", "%Y-%m-%d %H:%M:%S"];
+
+const CSV_HEADER: &[&str] = &[
+    "time",
+    "coin",
+    "dir",
+    "px",
+    "sz",
+    "ntl",
+    "fee",
+    "closedPnl",
+    "side",
+    "crossed",
+    "startPosition",
+    "hash",
+    "oid",
+    "tid",
+];
+
+const PARQUET_SCHEMA: &str = "
+    message user_fill {
+        REQUIRED INT64 timestamp;
+        REQUIRED BYTE_ARRAY coin (UTF8);
+        REQUIRED BYTE_ARRAY dir (UTF8);
+        REQUIRED BYTE_ARRAY side (UTF8);
+        REQUIRED DOUBLE price;
+        REQUIRED DOUBLE size;
+        REQUIRED DOUBLE fee;
+        REQUIRED DOUBLE closed_pnl;
+        REQUIRED DOUBLE start_position;
+        REQUIRED BOOLEAN crossed;
+        REQUIRED BYTE_ARRAY hash (UTF8);
+        REQUIRED INT64 order_id;
+        REQUIRED INT64 tid;
+    }
+";
+
+/// Path of the NDJSON log the fill writer appends to when no database is configured
+pub fn fill_log_path(user: H160) -> PathBuf {
+    PathBuf::from(format!("{:?}_fills.log", user))
+}
+
+/// Reads a `{user:?}_fills.log` NDJSON file. Lines that fail to parse are skipped with a warning.
+pub fn load_fills_from_log(path: &Path) -> Result<Vec<CustomUserFills>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut fills = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<CustomUserFills>(&line) {
+            Ok(fill) => fills.push(fill),
+            Err(e) => warn!("Skipping invalid fill {}: {}", line, e),
+        }
+    }
+    Ok(fills)
+}
+
+/// Reads a trade history CSV exported from the Hyperliquid UI or by `export_fills_to_csv`.
+///
+/// UI exports only carry time, coin, dir, px, sz, fee and closedPnl and their times are
+/// local to the browser, so `utc_offset` is needed to place them. The missing hash gets a
+/// stable `csv:` placeholder so importing the same file twice does not duplicate fills.
+pub fn load_fills_from_csv(path: &Path, utc_offset: FixedOffset) -> Result<Vec<CustomUserFills>> {
+    let mut reader = csv::Reader::from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let columns: HashMap<String, usize> = reader
+        .headers()?
+        .iter()
+        .enumerate()
+        .map(|(i, name)| (name.trim().to_string(), i))
+        .collect();
+    for required in ["time", "coin", "dir", "px", "sz", "fee", "closedPnl"] {
+        if !columns.contains_key(required) {
+            bail!("{}: missing column {}", path.display(), required);
+        }
+    }
+
+    let mut fills = Vec::new();
+    let mut occurrences: HashMap<String, i64> = HashMap::new();
+    for (line, record) in reader.records().enumerate() {
+        let record = record?;
+        let field = |name: &str| {
+            columns
+                .get(name)
+                .and_then(|&i| record.get(i))
+                .map(str::trim)
+        };
+        match csv_record_to_fill(&field, utc_offset) {
+            Ok(mut fill) => {
+                if fill.hash.is_empty() {
+                    // Identical rows in one export are separate fills, so they are numbered
+                    let key = format!(
+                        "{}{}:{}:{}:{}:{}",
+                        CSV_HASH_PREFIX, fill.timestamp, fill.coin, fill.dir, fill.price, fill.size
+                    );
+                    let occurrence = occurrences.entry(key.clone()).or_default();
+                    *occurrence += 1;
+                    fill.hash = format!("{}:{}", key, occurrence);
+                }
+                fills.push(fill);
+            }
+            Err(e) => warn!("Skipping CSV row {}: {:?}", line + 2, e),
+        }
+    }
+    Ok(fills)
+}
+
+fn csv_record_to_fill<'a>(
+    field: &impl Fn(&str) -> Option<&'a str>,
+    utc_offset: FixedOffset,
+) -> Result<CustomUserFills> {
+    let number = |name: &str| -> Result<f64> {
+        let value = field(name).unwrap_or_default();
+        // UI exports may append the fee token, e.g. "0.0213 USDC"
+        let value = value.split_whitespace().next().unwrap_or_default();
+        value
+            .replace(',', "")
+            .parse()
+            .with_context(|| format!("Invalid {}: {:?}", name, value))
+    };
+    let integer = |name: &str| -> Result<i64> {
+        match field(name) {
+            Some(value) if !value.is_empty() => value
+                .parse()
+                .with_context(|| format!("Invalid {}: {:?}", name, value)),
+            _ => Ok(0),
+        }
+    };
+
+    let dir = field("dir").unwrap_or_default().to_string();
+    let side = match field("side") {
+        Some(side) if !side.is_empty() => side.to_string(),
+        _ => side_from_dir(&dir)?.to_string(),
+    };
+
+    Ok(CustomUserFills {
+        closed_pnl: number("closedPnl")?,
+        coin: field("coin").unwrap_or_default().to_string(),
+        crossed: field("crossed") == Some("true"),
+        dir,
+        hash: field("hash").unwrap_or_default().to_string(),
+        order_id: integer("oid")?,
+        price: number("px")?,
+        side,
+        start_position: field("startPosition")
+            .filter(|value| !value.is_empty())
+            .map(|_| number("startPosition"))
+            .transpose()?
+            .unwrap_or(0.0),
+        size: number("sz")?,
+        timestamp: parse_time(field("time").unwrap_or_default(), utc_offset)?,
+        fee: number("fee")?,
+        tid: integer("tid")?,
+    })
+}
+
+fn side_from_dir(dir: &str) -> Result<&'static str> {
+    match dir {
+        "Open Long" | "Close Short" | "Buy" | "Short > Long" => Ok("B"),
+        "Open Short" | "Close Long" | "Sell" | "Long > Short" => Ok("A"),
+        _ => Err(anyhow!("Unknown dir {:?}", dir)),
+    }
+}
+
+// Epoch milliseconds, RFC 3339 or one of the UI formats in `utc_offset`
+fn parse_time(value: &str, utc_offset: FixedOffset) -> Result<i64> {
+    if let Ok(millis) = value.parse::<i64>() {
+        return Ok(millis);
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp_millis());
+    }
+    UI_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .and_then(|time| utc_offset.from_local_datetime(&time).single())
+        .map(|time| time.timestamp_millis())
+        .ok_or_else(|| anyhow!("Invalid time: {:?}", value))
+}
+
+/// Imports fills into the database. Duplicates within `fills` are dropped and fills already
+/// stored are upserted in place, so re-importing a file is harmless. UI export rows that
+/// match a fill stored from the exchange within the same second are skipped.
+/// Returns the number of distinct fills written.
+pub async fn import_fills(
+    storage: &dyn Storage,
+    user: H160,
+    fills: &[CustomUserFills],
+) -> Result<usize> {
+    // Same key as the database upsert, legacy logs have no tid to tell partial fills apart
+    let mut positions: HashMap<_, usize> = HashMap::new();
+    let mut unique: Vec<CustomUserFills> = Vec::new();
+    for fill in fills {
+        let key = (
+            fill.hash.as_str(),
+            fill.order_id,
+            fill.timestamp,
+            fill.price.to_bits(),
+            fill.size.to_bits(),
+            fill.start_position.to_bits(),
+        );
+        match positions.get(&key) {
+            Some(&position) => {
+                let kept = &mut unique[position];
+                kept.tid = kept.tid.max(fill.tid);
+            }
+            None => {
+                positions.insert(key, unique.len());
+                unique.push(fill.clone());
+            }
+        }
+    }
+
+    let unique = skip_stored_csv_fills(storage, user, unique).await?;
+
+    for chunk in unique.chunks(IMPORT_CHUNK_SIZE) {
+        save_fills_to_db(storage, chunk, user).await?;
+    }
+    Ok(unique.len())
+}
+
+// UI export rows have no hash or oid and whole-second times, so they can't meet the same
+// fill from the websocket or the API on the upsert key. They are matched on
+// (second, coin, dir, px, sz) instead, each stored fill absorbing at most one row.
+async fn skip_stored_csv_fills(
+    storage: &dyn Storage,
+    user: H160,
+    fills: Vec<CustomUserFills>,
+) -> Result<Vec<CustomUserFills>> {
+    let is_csv = |fill: &CustomUserFills| fill.hash.starts_with(CSV_HASH_PREFIX);
+    let csv_times = fills
+        .iter()
+        .filter(|fill| is_csv(fill))
+        .map(|fill| fill.timestamp);
+    let (Some(start_time), Some(end_time)) = (csv_times.clone().min(), csv_times.max()) else {
+        return Ok(fills);
+    };
+
+    let stored =
+        load_fills_from_db_with_time_filter(storage, user, start_time, end_time + 999).await?;
+    let mut available: HashMap<_, usize> = HashMap::new();
+    for fill in stored.iter().filter(|fill| !is_csv(fill)) {
+        *available.entry(csv_match_key(fill)).or_default() += 1;
+    }
+
+    let total = fills.len();
+    let remaining: Vec<CustomUserFills> = fills
+        .into_iter()
+        .filter(|fill| {
+            if !is_csv(fill) {
+                return true;
+            }
+            match available.get_mut(&csv_match_key(fill)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            }
+        })
+        .collect();
+    if remaining.len() < total {
+        info!(
+            "Skipped {} CSV rows already stored from the exchange",
+            total - remaining.len()
+        );
+    }
+    Ok(remaining)
+}
+
+fn csv_match_key(fill: &CustomUserFills) -> (i64, String, String, u64, u64) {
+    (
+        fill.timestamp.div_euclid(1000),
+        fill.coin.clone(),
+        fill.dir.clone(),
+        fill.price.to_bits(),
+        fill.size.to_bits(),
+    )
+}
+
+/// Exports the fills of `user` with `start_time <= timestamp <= end_time` (milliseconds).
+/// The format follows the extension of `path`: `.csv` or `.parquet`.
+pub async fn export_fills(
+    storage: &dyn Storage,
+    user: H160,
+    start_time: i64,
+    end_time: i64,
+    path: &Path,
+) -> Result<usize> {
+    let fills = load_fills_from_db_with_time_filter(storage, user, start_time, end_time).await?;
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("csv") => export_fills_to_csv(&fills, path)?,
+        Some("parquet") => export_fills_to_parquet(&fills, path)?,
+        _ => bail!("Unsupported export format: {}", path.display()),
+    }
+    Ok(fills.len())
+}
+
+/// Writes fills as CSV with the UI column names, readable again by `load_fills_from_csv`
+pub fn export_fills_to_csv(fills: &[CustomUserFills], path: &Path) -> Result<()> {
+    let mut writer = csv::Writer::from_path(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    writer.write_record(CSV_HEADER)?;
+    for fill in fills {
+        writer.write_record([
+            fill.timestamp.to_string(),
+            fill.coin.clone(),
+            fill.dir.clone(),
+            fill.price.to_string(),
+            fill.size.to_string(),
+            (fill.price * fill.size).to_string(),
+            fill.fee.to_string(),
+            fill.closed_pnl.to_string(),
+            fill.side.clone(),
+            fill.crossed.to_string(),
+            fill.start_position.to_string(),
+            fill.hash.clone(),
+            fill.order_id.to_string(),
+            fill.tid.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn export_fills_to_parquet(fills: &[CustomUserFills], path: &Path) -> Result<()> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut writer =
+        SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build()))?;
+
+    let strings = |value: fn(&CustomUserFills) -> &str| -> Vec<ByteArray> {
+        fills
+            .iter()
+            .map(|fill| ByteArray::from(value(fill)))
+            .collect()
+    };
+    let doubles =
+        |value: fn(&CustomUserFills) -> f64| -> Vec<f64> { fills.iter().map(value).collect() };
+    let integers =
+        |value: fn(&CustomUserFills) -> i64| -> Vec<i64> { fills.iter().map(value).collect() };
+
+    // Columns are written in schema order
+    let mut row_group = writer.next_row_group()?;
+    macro_rules! write_column {
+        ($data_type:ty, $values:expr) => {{
+            let mut column = row_group
+                .next_column()?
+                .ok_or_else(|| anyhow!("Parquet schema has fewer columns than written"))?;
+            column
+                .typed::<$data_type>()
+                .write_batch(&$values, None, None)?;
+            column.close()?;
+        }};
+    }
+    write_column!(Int64Type, integers(|fill| fill.timestamp));
+    write_column!(ByteArrayType, strings(|fill| &fill.coin));
+    write_column!(ByteArrayType, strings(|fill| &fill.dir));
+    write_column!(ByteArrayType, strings(|fill| &fill.side));
+    write_column!(DoubleType, doubles(|fill| fill.price));
+    write_column!(DoubleType, doubles(|fill| fill.size));
+    write_column!(DoubleType, doubles(|fill| fill.fee));
+    write_column!(DoubleType, doubles(|fill| fill.closed_pnl));
+    write_column!(DoubleType, doubles(|fill| fill.start_position));
+    write_column!(
+        BoolType,
+        fills.iter().map(|fill| fill.crossed).collect::<Vec<_>>()
+    );
+    write_column!(ByteArrayType, strings(|fill| &fill.hash));
+    write_column!(Int64Type, integers(|fill| fill.order_id));
+    write_column!(Int64Type, integers(|fill| fill.tid));
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hyperliquid::db::sqlite::SqliteStorage;
+
+    const UI_EXPORT: &str = "time,coin,dir,px,sz,ntl,fee,closedPnl
+14/11/2023 - 22:13:20,BTC,Open Long,100,1,100,0.05,0
+14/11/2023 - 22:13:20,BTC,Open Long,100,1,100,0.05,0
+14/11/2023 - 22:13:25,BTC,Close Long,101,2,202,0.1,2
+";
+
+    fn exchange_fill(timestamp: i64, start_position: f64, tid: i64) -> CustomUserFills {
+        CustomUserFills {
+            closed_pnl: 0.0,
+            coin: "BTC".to_string(),
+            crossed: true,
+            dir: "Open Long".to_string(),
+            hash: "0xabc".to_string(),
+            order_id: 7,
+            price: 100.0,
+            side: "B".to_string(),
+            start_position,
+            size: 1.0,
+            timestamp,
+            fee: 0.05,
+            tid,
+        }
+    }
+
+    fn load_ui_export(name: &str) -> Vec<CustomUserFills> {
+        let path = std::env::temp_dir().join(format!("{}_{}.csv", name, std::process::id()));
+        std::fs::write(&path, UI_EXPORT).unwrap();
+        let fills = load_fills_from_csv(&path, FixedOffset::east_opt(0).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        fills.unwrap()
+    }
+
+    async fn storage() -> SqliteStorage {
+        let storage = SqliteStorage::open("sqlite::memory:").unwrap();
+        storage.migrate().await.unwrap();
+        storage
+    }
+
+    #[test]
+    fn identical_ui_rows_are_numbered() {
+        let fills = load_ui_export("numbered");
+        assert_eq!(fills.len(), 3);
+        assert_eq!(fills[0].timestamp, 1_700_000_000_000);
+        assert_eq!(fills[0].hash, "csv:1700000000000:BTC:Open Long:100:1:1");
+        assert_eq!(fills[1].hash, "csv:1700000000000:BTC:Open Long:100:1:2");
+        assert_eq!(fills[2].side, "A");
+    }
+
+    #[tokio::test]
+    async fn overlapping_ui_import_skips_stored_fills() {
+        let storage = storage().await;
+        let user = H160::zero();
+        let stored = [
+            exchange_fill(1_700_000_000_123, 0.0, 11),
+            exchange_fill(1_700_000_000_456, 1.0, 12),
+        ];
+        save_fills_to_db(&storage, &stored, user).await.unwrap();
+
+        let fills = load_ui_export("overlap");
+        assert_eq!(import_fills(&storage, user, &fills).await.unwrap(), 1);
+        assert_eq!(import_fills(&storage, user, &fills).await.unwrap(), 1);
+
+        let all = storage.load_fills(user).await.unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all.iter().map(|fill| fill.size).sum::<f64>(), 4.0);
+    }
+
+    #[tokio::test]
+    async fn legacy_partial_fills_without_tid_are_kept() {
+        let storage = storage().await;
+        let user = H160::zero();
+        let fills = [
+            exchange_fill(1_000, 0.0, 0),
+            exchange_fill(1_000, 1.0, 0),
+            exchange_fill(1_000, 1.0, 0),
+        ];
+        assert_eq!(import_fills(&storage, user, &fills).await.unwrap(), 2);
+
+        save_fills_to_db(&storage, &[exchange_fill(1_000, 1.0, 42)], user)
+            .await
+            .unwrap();
+        let all = storage.load_fills(user).await.unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all.iter().map(|fill| fill.tid).max(), Some(42));
+    }
+}
@@ -0,0 +1,216 @@
+use crate::hyperliquid::db::{save_fills_to_db, Storage};
+use crate::hyperliquid::fill_log::{fill_log_path, load_fills_from_log};
+use crate::hyperliquid::model::CustomUserFills;
+use anyhow::{Context, Result};
+use ethers::types::H160;
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::{timeout, timeout_at, Duration, Instant};
+
+const MAX_BATCH_FILLS: usize = 500;
+const BATCH_WINDOW_MS: u64 = 200;
+const RETRY_INTERVAL_SECS: u64 = 10;
+const SPOOL_SUFFIX: &str = "_fills.spool";
+
+pub struct FillBatch {
+    pub user: H160,
+    pub fills: Vec<CustomUserFills>,
+}
+
+/// Spawns the task that persists fills in the order they arrive.
+///
+/// Without a database the fills are appended to `{user:?}_fills.log`. With one they are
+/// upserted in batches; while the database is unreachable they are spooled as NDJSON to
+/// `{user:?}_fills.spool` and drained back once the connection returns.
+pub fn spawn_fill_writer(storage: Option<Arc<dyn Storage>>) -> UnboundedSender<FillBatch> {
+    let (sender, receiver) = unbounded_channel();
+    tokio::spawn(run_fill_writer(receiver, storage));
+    sender
+}
+
+async fn run_fill_writer(
+    mut receiver: UnboundedReceiver<FillBatch>,
+    storage: Option<Arc<dyn Storage>>,
+) {
+    let Some(storage) = storage else {
+        while let Some(batch) = receiver.recv().await {
+            if let Err(e) = append_fills_to_file(&fill_log_path(batch.user), &batch.fills) {
+                error!("Failed to append fills to file: {}", e);
+            }
+        }
+        return;
+    };
+
+    let mut writer = DbWriter::new(storage);
+    loop {
+        // Wakes up without new fills too, so spooled fills drain soon after an outage
+        let first = match timeout(Duration::from_secs(RETRY_INTERVAL_SECS), receiver.recv()).await {
+            Ok(Some(batch)) => Some(batch),
+            Ok(None) => break,
+            Err(_) => None,
+        };
+
+        let mut batches = Vec::new();
+        if let Some(batch) = first {
+            batches.push(batch);
+            collect_batches(&mut receiver, &mut batches).await;
+        }
+        writer.write(batches).await;
+    }
+}
+
+// Gathers what arrives within the batch window, up to MAX_BATCH_FILLS fills
+async fn collect_batches(
+    receiver: &mut UnboundedReceiver<FillBatch>,
+    batches: &mut Vec<FillBatch>,
+) {
+    let deadline = Instant::now() + Duration::from_millis(BATCH_WINDOW_MS);
+    let mut count: usize = batches.iter().map(|batch| batch.fills.len()).sum();
+    while count < MAX_BATCH_FILLS {
+        match timeout_at(deadline, receiver.recv()).await {
+            Ok(Some(batch)) => {
+                count += batch.fills.len();
+                batches.push(batch);
+            }
+            _ => break,
+        }
+    }
+}
+
+struct DbWriter {
+    storage: Arc<dyn Storage>,
+    next_retry: Instant,
+    spooled: HashSet<H160>,
+}
+
+impl DbWriter {
+    fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            next_retry: Instant::now(),
+            spooled: find_spooled_users(),
+        }
+    }
+
+    async fn write(&mut self, batches: Vec<FillBatch>) {
+        let mut by_user: HashMap<H160, Vec<CustomUserFills>> = HashMap::new();
+        for batch in batches {
+            by_user.entry(batch.user).or_default().extend(batch.fills);
+        }
+
+        if self.is_available() {
+            self.drain_spools().await;
+        }
+
+        for (user, fills) in by_user {
+            // Spooled fills go first so the order in the database stays the arrival order
+            if !self.spooled.contains(&user) && self.is_available() {
+                match save_fills_to_db(self.storage.as_ref(), &fills, user).await {
+                    Ok(()) => continue,
+                    Err(e) => {
+                        warn!("Failed to save fills to database, spooling: {:?}", e);
+                        self.back_off();
+                    }
+                }
+            }
+            self.spool(user, &fills);
+        }
+    }
+
+    // After a failure the database is left alone for a while instead of timing out per batch
+    fn is_available(&self) -> bool {
+        Instant::now() >= self.next_retry
+    }
+
+    fn back_off(&mut self) {
+        self.next_retry = Instant::now() + Duration::from_secs(RETRY_INTERVAL_SECS);
+    }
+
+    async fn drain_spools(&mut self) {
+        let users: Vec<H160> = self.spooled.iter().copied().collect();
+        for user in users {
+            if !self.is_available() {
+                return;
+            }
+            match drain_spool(self.storage.as_ref(), user).await {
+                Ok(count) => {
+                    info!(
+                        "Drained {} spooled fills of {:?} into database",
+                        count, user
+                    );
+                    self.spooled.remove(&user);
+                }
+                Err(e) => {
+                    warn!("Failed to drain spooled fills of {:?}: {:?}", user, e);
+                    self.back_off();
+                }
+            }
+        }
+    }
+
+    fn spool(&mut self, user: H160, fills: &[CustomUserFills]) {
+        match append_fills_to_file(&spool_path(user), fills) {
+            Ok(()) => {
+                self.spooled.insert(user);
+            }
+            Err(e) => error!(
+                "Failed to spool {} fills, they are lost: {}",
+                fills.len(),
+                e
+            ),
+        }
+    }
+}
+
+// Upserts make a partially drained spool safe to drain again
+async fn drain_spool(storage: &dyn Storage, user: H160) -> Result<usize> {
+    let path = spool_path(user);
+    if !path.exists() {
+        return Ok(0);
+    }
+    let fills = load_fills_from_log(&path)?;
+
+    for chunk in fills.chunks(MAX_BATCH_FILLS) {
+        save_fills_to_db(storage, chunk, user).await?;
+    }
+    fs::remove_file(&path).context("Failed to remove drained fill spool")?;
+    Ok(fills.len())
+}
+
+fn append_fills_to_file(path: &Path, fills: &[CustomUserFills]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Failed to open user fills file")?;
+
+    for fill in fills {
+        let json = serde_json::to_string(fill).context("Failed to serialize fill")?;
+        writeln!(file, "{}", json).context("Failed to write to user fills file")?;
+    }
+
+    Ok(())
+}
+
+fn spool_path(user: H160) -> PathBuf {
+    PathBuf::from(format!("{:?}{}", user, SPOOL_SUFFIX))
+}
+
+// Spools left over from a previous run are drained as well
+fn find_spooled_users() -> HashSet<H160> {
+    let Ok(entries) = fs::read_dir(".") else {
+        return HashSet::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            name.strip_suffix(SPOOL_SUFFIX)?.parse::<H160>().ok()
+        })
+        .collect()
+}
@@ -1,20 +1,43 @@
+use super::metadata::{AssetMetadata, AssetMetadataMap, PerpMetaResponse, SpotMetaResponse};
 use super::order::{LimitOrderParams, MarketOrderParams};
+use super::order_audit::{OrderAuditor, OrderRequestRecord};
+use super::rate_limit::{
+    response_weight, RateLimitConfig, RateLimitStatus, RateLimiter, RequestPriority,
+    EXCHANGE_WEIGHT, INFO_WEIGHT, LIGHT_INFO_WEIGHT,
+};
+use super::retry::RetryConfig;
 use crate::hyperliquid::model::{
     CustomCandle, CustomL2Book, CustomOpenOrders, CustomOrderStatus, CustomTrade, CustomUserFills,
-    CustomUserTokenBalance, TokenDetails,
+    CustomUserTokenBalance, InfoUserFill, TokenDetails,
 };
-use anyhow::{anyhow, Context, Result};
-use ethers::signers::LocalWallet;
+use crate::utils::logger::AUDIT_TARGET;
+use crate::utils::metrics::{record_order, HTTP_REQUEST_DURATION, HTTP_RETRIES};
+use anyhow::{anyhow, bail, Context, Result};
+use arc_swap::ArcSwap;
+use chrono::Utc;
+use ethers::signers::{LocalWallet, Signer};
 use ethers::types::H160;
 use hyperliquid_rust_sdk::{
     BaseUrl, ClientCancelRequest, ClientCancelRequestCloid, ClientLimit, ClientOrder,
     ClientOrderRequest, ExchangeClient, ExchangeDataStatus, ExchangeResponseStatus,
-    FundingHistoryResponse, InfoClient, UserFundingResponse, UserStateResponse,
+    FundingHistoryResponse, InfoClient, OrderStatusResponse, UserFundingResponse,
+    UserStateResponse,
 };
-use log::{debug, error};
-use std::collections::HashMap;
+use log::{debug, error, info, warn};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
 use uuid::Uuid;
 
+// Quote token of the spot pairs positions are closed through
+const SPOT_QUOTE_TOKEN: &str = "USDC";
+// Maximum number of fills in one userFillsByTime response
+const USER_FILLS_PAGE_SIZE: usize = 2000;
+
 #[derive(Debug, Clone)]
 pub struct AssetInfo {
     pub internal_name: String,
@@ -22,14 +45,34 @@ pub struct AssetInfo {
     pub sz_decimals: u8,
 }
 
+impl From<&AssetMetadata> for AssetInfo {
+    fn from(asset: &AssetMetadata) -> Self {
+        AssetInfo {
+            internal_name: asset.internal_name.clone(),
+            index: asset.index,
+            sz_decimals: asset.sz_decimals,
+        }
+    }
+}
+
 pub struct HttpClient {
     info: InfoClient,
     exchange: ExchangeClient,
-    token_info: HashMap<String, AssetInfo>,
+    metadata: ArcSwap<AssetMetadataMap>,
+    account_address: H160,
+    order_auditor: Option<OrderAuditor>,
+    rate_limiter: RateLimiter,
+    retry: RetryConfig,
 }
 
 impl HttpClient {
-    pub async fn new(is_mainnet: bool, wallet: LocalWallet) -> Result<Self> {
+    /// Creates a client that signs with `wallet`. When `vault_address` is set, orders are
+    /// placed on behalf of that vault or sub-account instead of the signer's own account.
+    pub async fn new(
+        is_mainnet: bool,
+        wallet: LocalWallet,
+        vault_address: Option<H160>,
+    ) -> Result<Self> {
         let base_url = if is_mainnet {
             BaseUrl::Mainnet
         } else {
@@ -40,155 +83,197 @@ impl HttpClient {
             .await
             .context("Failed to initialize InfoClient")?;
 
-        let exchange = ExchangeClient::new(None, wallet, Some(base_url), None, None)
+        let account_address = vault_address.unwrap_or_else(|| wallet.address());
+        let exchange = ExchangeClient::new(None, wallet, Some(base_url), None, vault_address)
             .await
             .context("Failed to initialize ExchangeClient")?;
 
-        let mut token_info = HashMap::new();
-        token_info.extend(Self::build_spot_asset_map(&info).await?);
-        token_info.extend(Self::build_perp_asset_map(&info).await?);
-
-        Ok(Self {
+        let client = Self {
             info,
             exchange,
-            token_info,
-        })
+            metadata: ArcSwap::from_pointee(AssetMetadataMap::default()),
+            account_address,
+            order_auditor: None,
+            rate_limiter: RateLimiter::new(RateLimitConfig::default()),
+            retry: RetryConfig::default(),
+        };
+        client.refresh_metadata().await?;
+        Ok(client)
+    }
+
+    /// Overrides the account address, e.g. with the main account when `wallet` is an
+    /// approved agent (API) wallet that only signs orders.
+    pub fn with_account_address(mut self, account_address: H160) -> Self {
+        self.account_address = account_address;
+        self
+    }
+
+    /// Records every order and cancel request with its response, see `order_audit`
+    pub fn with_order_auditor(mut self, order_auditor: OrderAuditor) -> Self {
+        self.order_auditor = Some(order_auditor);
+        self
+    }
+
+    /// Replaces the default budget of 1200 weight per minute
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = RateLimiter::new(config);
+        self
+    }
+
+    /// Replaces the default 10s timeout and 3 retries
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Request weight left right now, so strategies can poll less while it runs low
+    pub fn rate_limit_status(&self) -> RateLimitStatus {
+        self.rate_limiter.status()
+    }
+
+    /// Address of the account that orders are placed for (the vault if one is set).
+    pub fn account_address(&self) -> H160 {
+        self.account_address
     }
 
-    async fn build_spot_asset_map(info: &InfoClient) -> Result<HashMap<String, AssetInfo>> {
-        let spot_meta = info
-            .spot_meta()
+    /// Approves a newly generated agent wallet for the signer's account and returns the
+    /// agent's private key. Has to be called with the main wallet, not with an agent.
+    pub async fn approve_agent(&self) -> Result<String> {
+        self.rate_limiter
+            .acquire(EXCHANGE_WEIGHT, RequestPriority::Order)
+            .await?;
+        let (agent_secret, response_status) = self
+            .timed("approve_agent", self.exchange.approve_agent(None))
+            .await
+            .context("Failed to approve agent")?;
+
+        match response_status {
+            ExchangeResponseStatus::Ok(_) => Ok(agent_secret),
+            ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
+        }
+    }
+
+    /// Fetches perp and spot metadata again, so assets listed since the last refresh
+    /// can be traded without a restart
+    pub async fn refresh_metadata(&self) -> Result<()> {
+        let perp_meta: PerpMetaResponse = self
+            .post_info("refresh_metadata", serde_json::json!({"type": "meta"}))
+            .await
+            .context("Failed to fetch Perp meta")?;
+        let spot_meta: SpotMetaResponse = self
+            .post_info("refresh_metadata", serde_json::json!({"type": "spotMeta"}))
             .await
             .context("Failed to fetch Spot meta")?;
-        let mut asset_map = HashMap::new();
-
-        for meta in spot_meta.universe.iter() {
-            if meta.tokens.len() == 2 {
-                let token_0_index = meta.tokens[0];
-                let token_1_index = meta.tokens[1];
-
-                if let (Some(token_0), Some(token_1)) = (
-                    spot_meta.tokens.iter().find(|t| t.index == token_0_index),
-                    spot_meta.tokens.iter().find(|t| t.index == token_1_index),
-                ) {
-                    let key = format!("{}/{}", token_0.name, token_1.name);
-                    asset_map.insert(
-                        key,
-                        AssetInfo {
-                            internal_name: meta.name.clone(),
-                            index: meta.index + 10000,
-                            sz_decimals: token_0.sz_decimals,
-                        },
-                    );
+
+        let metadata = AssetMetadataMap::build(&perp_meta, &spot_meta);
+        let previous = self.metadata.swap(Arc::new(metadata));
+        if !previous.is_empty() {
+            let metadata = self.metadata.load();
+            for asset in metadata.assets() {
+                if previous.get_by_index(asset.index).is_none() {
+                    info!("New asset listed: {} ({})", asset.name, asset.internal_name);
                 }
             }
         }
+        Ok(())
+    }
 
-        Ok(asset_map)
+    /// Metadata of a display name ("HYPE/USDC"), internal name ("@107") or asset index
+    pub fn asset_metadata(&self, key: &str) -> Option<Arc<AssetMetadata>> {
+        self.metadata.load().get(key)
     }
 
-    async fn build_perp_asset_map(info: &InfoClient) -> Result<HashMap<String, AssetInfo>> {
-        let perp_meta = info.meta().await.context("Failed to fetch Perp meta")?;
-        let mut asset_map = HashMap::new();
+    /// Like `asset_metadata`, with an error for unknown coins
+    pub fn resolve_asset(&self, key: &str) -> Result<Arc<AssetMetadata>> {
+        self.metadata.load().resolve(key)
+    }
 
-        for (index, meta) in perp_meta.universe.iter().enumerate() {
-            asset_map.insert(
-                meta.name.clone(),
-                AssetInfo {
-                    internal_name: meta.name.clone(),
-                    index,
-                    sz_decimals: meta.sz_decimals as u8,
-                },
-            );
-        }
+    /// Every listed perp and spot pair as of the last refresh
+    pub fn all_assets(&self) -> Vec<Arc<AssetMetadata>> {
+        self.metadata.load().assets().to_vec()
+    }
 
-        Ok(asset_map)
+    pub fn get_asset_info(&self, symbol: &str) -> Option<AssetInfo> {
+        self.asset_metadata(symbol)
+            .map(|asset| AssetInfo::from(asset.as_ref()))
     }
 
-    pub fn get_asset_info(&self, symbol: &str) -> Option<&AssetInfo> {
-        self.token_info.get(symbol)
+    /// Symbol for an internal name as used in fills and websocket data (e.g. "@107" -> "HYPE/USDC")
+    pub fn find_symbol(&self, internal_name: &str) -> Option<String> {
+        self.metadata
+            .load()
+            .get_by_internal_name(internal_name)
+            .map(|asset| asset.name.clone())
     }
 
     pub async fn limit_order(&self, params: LimitOrderParams) -> Result<u64> {
         let reduce_only = params.reduce_only.unwrap_or(false);
-        let time_in_force = params.time_in_force.unwrap_or("Gtc".to_string());
-
-        let order = ClientOrderRequest {
-            asset: params.asset,
+        let time_in_force = params.time_in_force.clone().unwrap_or("Gtc".to_string());
+
+        let mut record = OrderRequestRecord::new("limit_order", &params.asset);
+        record.cloid = params.cloid.map(|cloid| cloid.to_string());
+        record.side = Some(side_code(params.is_buy));
+        record.price = Some(params.price);
+        record.size = Some(params.size);
+        record.order_type = Some(time_in_force.clone());
+        record.reduce_only = reduce_only;
+        let request_text = format!("{:?}", params);
+
+        let order = || ClientOrderRequest {
+            asset: params.asset.clone(),
             is_buy: params.is_buy,
             reduce_only,
             limit_px: params.price,
             sz: params.size,
             cloid: params.cloid,
-            order_type: ClientOrder::Limit(ClientLimit { tif: time_in_force }),
+            order_type: ClientOrder::Limit(ClientLimit {
+                tif: time_in_force.clone(),
+            }),
         };
 
-        let response_status = self
-            .exchange
-            .order(order, None)
+        let result = self
+            .place_order("limit_order", record, &request_text, params.cloid, order)
             .await
-            .context("Failed to place limit order")?;
-
-        match response_status {
-            ExchangeResponseStatus::Ok(exchange_response) => {
-                let oid = exchange_response
-                    .data
-                    .and_then(|data| {
-                        data.statuses.first().map(|status| match status {
-                            ExchangeDataStatus::Filled(order) => Some(order.oid),
-                            ExchangeDataStatus::Resting(order) => Some(order.oid),
-                            _ => None,
-                        })
-                    })
-                    .flatten()
-                    .context("No valid statuses or unexpected status in exchange response.")?;
-                Ok(oid)
-            }
-            ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
-        }
+            .context("Failed to place limit order");
+        record_order("limit", result.is_ok());
+        result
     }
 
     pub async fn market_order(&self, params: MarketOrderParams) -> Result<u64> {
         let (adjusted_price, sz_decimals) = self
             .calculate_slippage_price(&params.asset, params.is_buy, 0.01)
             .await
-            .unwrap();
+            .context("Failed to calculate market order price")?;
+        let size = round_to_decimals(params.size, sz_decimals);
+        let reduce_only = params.reduce_only.unwrap_or(false);
 
-        let order = ClientOrderRequest {
-            asset: params.asset,
+        let mut record = OrderRequestRecord::new("market_order", &params.asset);
+        record.cloid = params.cloid.map(|cloid| cloid.to_string());
+        record.side = Some(side_code(params.is_buy));
+        record.price = Some(adjusted_price);
+        record.size = Some(size);
+        record.order_type = Some("Ioc".to_string());
+        record.reduce_only = reduce_only;
+        let request_text = format!("{:?} limit_px={} sz={}", params, adjusted_price, size);
+
+        let order = || ClientOrderRequest {
+            asset: params.asset.clone(),
             is_buy: params.is_buy,
-            reduce_only: false,
+            reduce_only,
             limit_px: adjusted_price,
-            sz: round_to_decimals(params.size, sz_decimals),
+            sz: size,
             cloid: params.cloid,
             order_type: ClientOrder::Limit(ClientLimit {
                 tif: "Ioc".to_string(),
             }),
         };
 
-        let response_status = self
-            .exchange
-            .order(order, None)
+        let result = self
+            .place_order("market_order", record, &request_text, params.cloid, order)
             .await
-            .context("Failed to place market order")?;
-
-        match response_status {
-            ExchangeResponseStatus::Ok(exchange_response) => {
-                let oid = exchange_response
-                    .data
-                    .and_then(|data| {
-                        data.statuses.first().map(|status| match status {
-                            ExchangeDataStatus::Filled(order) => Some(order.oid),
-                            ExchangeDataStatus::Resting(order) => Some(order.oid),
-                            _ => None,
-                        })
-                    })
-                    .flatten()
-                    .context("No valid statuses or unexpected status in exchange response.")?;
-                Ok(oid)
-            }
-            ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
-        }
+            .context("Failed to place market order");
+        record_order("market", result.is_ok());
+        result
     }
 
     async fn calculate_slippage_price(
@@ -197,14 +282,9 @@ impl HttpClient {
         is_buy: bool,
         slippage: f64,
     ) -> Result<(f64, u32)> {
-        let asset_info = self
-            .get_asset_info(asset)
-            .context(format!("sz_decimals not found for asset {}", asset))?;
-
+        let asset_info = self.resolve_asset(asset)?;
         let sz_decimals = asset_info.sz_decimals;
-        let max_decimals: u32 = if asset_info.index < 10000 { 6 } else { 8 };
-
-        let price_decimals = max_decimals.saturating_sub(sz_decimals as u32);
+        let price_decimals = asset_info.price_decimals;
         let all_mids = self
             .fetch_all_mids()
             .await
@@ -226,72 +306,312 @@ impl HttpClient {
         Ok((adjusted_price, sz_decimals.into()))
     }
 
-    pub async fn close_position(&self) {
-        todo!("close_position")
+    /// Closes the whole position in `asset` with a reduce-only IOC order at 1% slippage and
+    /// returns its oid, or None when there is nothing to close. The size is read from the
+    /// exchange: `clearinghouseState` for perps, the available base token balance for spot.
+    pub async fn close_position(&self, asset: &str) -> Result<Option<u64>> {
+        let asset = self.resolve_asset(asset)?;
+        let params = match &asset.base_token {
+            None => {
+                let amount = self
+                    .fetch_perp_positions()
+                    .await?
+                    .get(&asset.internal_name)
+                    .copied()
+                    .unwrap_or(0.0);
+                MarketOrderParams::new(asset.name.clone(), amount < 0.0, amount.abs())
+                    .reduce_only(true)
+            }
+            // Spot has no reduce-only, a sell of at most the balance can't open a short
+            Some(base_token) => {
+                let available = self
+                    .fetch_token_balances(self.account_address)
+                    .await?
+                    .into_iter()
+                    .find(|balance| balance.coin == base_token.name)
+                    .map_or(0.0, |balance| balance.total - balance.hold);
+                let size = floor_to_decimals(available.max(0.0), asset.sz_decimals.into());
+                MarketOrderParams::new(asset.name.clone(), false, size)
+            }
+        };
+        if params.size == 0.0 {
+            return Ok(None);
+        }
+
+        info!(
+            "Closing {} {} of {}",
+            if params.is_buy { "short" } else { "long" },
+            params.size,
+            asset.name
+        );
+        self.market_order(params).await.map(Some)
     }
 
-    pub async fn cancel_order(&self, asset: String, oid: u64) -> Result<String> {
-        let request = ClientCancelRequest { asset, oid };
-        let response_status = self
-            .exchange
-            .cancel(request, None)
-            .await
-            .context("Failed to cancel order")?;
+    /// Symbols with a position on the exchange: perps with a non-zero size and spot pairs
+    /// against USDC of every other token with a balance
+    pub async fn fetch_open_positions(&self) -> Result<Vec<String>> {
+        let mut symbols: Vec<String> = self
+            .fetch_perp_positions()
+            .await?
+            .into_iter()
+            .filter(|(_, amount)| *amount != 0.0)
+            .filter_map(|(coin, _)| self.find_symbol(&coin))
+            .collect();
 
-        match response_status {
-            ExchangeResponseStatus::Ok(exchange_response) => {
-                if let Some(data) = exchange_response.data {
-                    let success = data
-                        .statuses
-                        .iter()
-                        .any(|status| matches!(status, ExchangeDataStatus::Success));
-
-                    if success {
-                        let success_msg = "Order cancelled successfully".to_string();
-                        return Ok(success_msg);
-                    }
-                }
-                Err(anyhow!(
-                    "Unexpected response format: No success status found."
-                ))
+        for balance in self.fetch_token_balances(self.account_address).await? {
+            if balance.coin == SPOT_QUOTE_TOKEN || balance.total == 0.0 {
+                continue;
+            }
+            let symbol = format!("{}/{}", balance.coin, SPOT_QUOTE_TOKEN);
+            if self.asset_metadata(&symbol).is_some() {
+                symbols.push(symbol);
+            } else {
+                warn!("No {} pair to close the {} balance", symbol, balance.coin);
             }
-            ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
         }
+        Ok(symbols)
+    }
+
+    // Signed size per coin from clearinghouseState
+    async fn fetch_perp_positions(&self) -> Result<HashMap<String, f64>> {
+        let user_state = self.fetch_user_state(self.account_address).await?;
+        user_state
+            .asset_positions
+            .into_iter()
+            .map(|asset_position| {
+                let position = asset_position.position;
+                let amount = position
+                    .szi
+                    .parse::<f64>()
+                    .with_context(|| format!("Invalid position size of {}", position.coin))?;
+                Ok((position.coin, amount))
+            })
+            .collect()
+    }
+
+    pub async fn cancel_order(&self, asset: String, oid: u64) -> Result<String> {
+        self.rate_limiter
+            .acquire(EXCHANGE_WEIGHT, RequestPriority::Cancel)
+            .await?;
+        let mut record = OrderRequestRecord::new("cancel_order", &asset);
+        record.order_id = Some(oid as i64);
+        let request_text = format!("asset={} oid={}", asset, oid);
+
+        let request = ClientCancelRequest { asset, oid };
+        let result = self
+            .audited(
+                record,
+                &request_text,
+                self.timed("cancel_order", self.exchange.cancel(request, None)),
+            )
+            .await
+            .context("Failed to cancel order")
+            .and_then(cancel_result_from_response);
+        record_order("cancel", result.is_ok());
+        result
     }
 
     pub async fn cancel_by_cloid(&self, asset: String, cloid: Uuid) -> Result<String> {
+        self.rate_limiter
+            .acquire(EXCHANGE_WEIGHT, RequestPriority::Cancel)
+            .await?;
+        let mut record = OrderRequestRecord::new("cancel_by_cloid", &asset);
+        record.cloid = Some(cloid.to_string());
+        let request_text = format!("asset={} cloid={}", asset, cloid);
+
         let request = ClientCancelRequestCloid { asset, cloid };
-        let response_status = self
-            .exchange
-            .cancel_by_cloid(request, None)
+        let result = self
+            .audited(
+                record,
+                &request_text,
+                self.timed(
+                    "cancel_by_cloid",
+                    self.exchange.cancel_by_cloid(request, None),
+                ),
+            )
             .await
-            .context("Failed to cancel order by cloid")?;
+            .context("Failed to cancel order by cloid")
+            .and_then(cancel_result_from_response);
+        record_order("cancel", result.is_ok());
+        result
+    }
 
-        match response_status {
-            ExchangeResponseStatus::Ok(exchange_response) => {
-                if let Some(data) = exchange_response.data {
-                    let success = data
-                        .statuses
-                        .iter()
-                        .any(|status| matches!(status, ExchangeDataStatus::Success));
-
-                    if success {
-                        let success_msg = "Order cancelled successfully".to_string();
-                        return Ok(success_msg);
-                    }
+    // Sends an order, retrying failed or timed out attempts only when it has a cloid and the
+    // exchange has no order with that cloid yet. Rejections by the exchange are never retried.
+    async fn place_order(
+        &self,
+        method: &str,
+        record: OrderRequestRecord,
+        request_text: &str,
+        cloid: Option<Uuid>,
+        order: impl Fn() -> ClientOrderRequest,
+    ) -> Result<u64> {
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter
+                .acquire(EXCHANGE_WEIGHT, RequestPriority::Order)
+                .await?;
+            let response = self
+                .audited(
+                    record.clone(),
+                    request_text,
+                    self.timed(method, self.exchange.order(order(), None)),
+                )
+                .await;
+            let error = match response {
+                Ok(response) => return order_id_from_response(response),
+                Err(e) => e,
+            };
+            let Some(cloid) =
+                cloid.filter(|_| self.retry.retry_orders && attempt < self.retry.max_retries)
+            else {
+                return Err(error);
+            };
+
+            // The backoff also gives a request that is still in flight time to land
+            let backoff = self.retry.backoff(attempt);
+            attempt += 1;
+            warn!(
+                "{} with cloid {} failed ({:#}), checking its status before retry {}/{} in {}ms",
+                method,
+                cloid,
+                error,
+                attempt,
+                self.retry.max_retries,
+                backoff.as_millis()
+            );
+            tokio::time::sleep(backoff).await;
+
+            let status = match self.fetch_order_by_cloid(self.account_address, cloid).await {
+                Ok(status) => status,
+                Err(e) => {
+                    error!(
+                        "Not retrying {}, status of cloid {} is unknown: {:?}",
+                        method, cloid, e
+                    );
+                    return Err(error);
+                }
+            };
+            match status.status.as_str() {
+                "unknown" => HTTP_RETRIES.with_label_values(&[method]).inc(),
+                "rejected" => bail!("Order with cloid {} was rejected", cloid),
+                _ => {
+                    info!(
+                        "{} with cloid {} reached the exchange as oid {} ({}), not resending",
+                        method, cloid, status.order_id, status.status
+                    );
+                    return Ok(status.order_id);
                 }
-                Err(anyhow!(
-                    "Unexpected response format: No success status found."
-                ))
             }
-            ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
         }
     }
 
+    // Info queries are idempotent, so every failure or timeout is retried with backoff
+    async fn info_request<T, E, F>(
+        &self,
+        method: &str,
+        weight: u32,
+        request: impl Fn() -> F,
+    ) -> Result<T>
+    where
+        F: Future<Output = Result<T, E>>,
+        E: Into<anyhow::Error>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter
+                .acquire(weight, RequestPriority::Info)
+                .await?;
+            let error = match self.timed(method, request()).await {
+                Ok(response) => return Ok(response),
+                Err(e) => e,
+            };
+            if attempt >= self.retry.max_retries {
+                return Err(error);
+            }
+
+            let backoff = self.retry.backoff(attempt);
+            attempt += 1;
+            warn!(
+                "{} failed ({:#}), retry {}/{} in {}ms",
+                method,
+                error,
+                attempt,
+                self.retry.max_retries,
+                backoff.as_millis()
+            );
+            HTTP_RETRIES.with_label_values(&[method]).inc();
+            tokio::time::sleep(backoff).await;
+        }
+    }
+
+    async fn timed<T, E: Into<anyhow::Error>>(
+        &self,
+        method: &str,
+        request: impl Future<Output = Result<T, E>>,
+    ) -> Result<T> {
+        let timeout = self.retry.timeout(method);
+        // Each attempt on its own, without rate limit waits and retry backoff
+        let _timer = HTTP_REQUEST_DURATION
+            .with_label_values(&[method])
+            .start_timer();
+        match tokio::time::timeout(timeout, request).await {
+            Ok(response) => response.map_err(Into::into),
+            Err(_) => Err(anyhow!(
+                "{} timed out after {}ms",
+                method,
+                timeout.as_millis()
+            )),
+        }
+    }
+
+    // Sends the request, writes both sides to the audit log and hands the completed
+    // record to the order auditor
+    async fn audited<E: Debug>(
+        &self,
+        mut record: OrderRequestRecord,
+        request_text: &str,
+        request: impl Future<Output = Result<ExchangeResponseStatus, E>>,
+    ) -> Result<ExchangeResponseStatus, E> {
+        info!(
+            target: AUDIT_TARGET,
+            action = record.action.as_str(),
+            cloid = record.cloid.as_deref(),
+            oid = record.order_id;
+            "{} request: {}", record.action, request_text
+        );
+        record.sent_at = Utc::now().timestamp_millis();
+        let started = Instant::now();
+        let response = request.await;
+        record.latency_ms = started.elapsed().as_millis() as i64;
+
+        let (status, order_id) = order_status(&response);
+        record.status = status.to_string();
+        record.order_id = record.order_id.or(order_id.map(|oid| oid as i64));
+        record.response = match &response {
+            Ok(status) => format!("{:?}", status),
+            Err(e) => format!("{:?}", e),
+        };
+        info!(
+            target: AUDIT_TARGET,
+            action = record.action.as_str(),
+            cloid = record.cloid.as_deref(),
+            oid = record.order_id,
+            latency_ms = record.latency_ms;
+            "{} response: {}", record.action, record.response
+        );
+
+        if let Some(auditor) = &self.order_auditor {
+            auditor.record(record);
+        }
+        response
+    }
+
     pub async fn fetch_open_orders(&self, address: H160) -> Result<Vec<CustomOpenOrders>> {
         let response = self
-            .info
-            .open_orders(address)
+            .info_request("fetch_open_orders", INFO_WEIGHT, || {
+                self.info.open_orders(address)
+            })
             .await
             .context("Failed to fetch open orders")?;
 
@@ -300,14 +620,34 @@ impl HttpClient {
         Ok(open_orders)
     }
 
-    pub async fn fetch_order_by_cloid(&self, _address: String, _cloid: String) -> Result<()> {
-        todo!("fetch_order_by_cloid")
+    /// Status is "unknown" when the exchange has no order with this cloid
+    pub async fn fetch_order_by_cloid(
+        &self,
+        address: H160,
+        cloid: Uuid,
+    ) -> Result<CustomOrderStatus> {
+        let request = serde_json::json!({
+            "type": "orderStatus",
+            "user": address,
+            "oid": format!("0x{}", cloid.simple()),
+        });
+        let data = serde_json::to_string(&request).context("Failed to serialize request")?;
+        let response = self
+            .info_request("fetch_order_by_cloid", LIGHT_INFO_WEIGHT, || {
+                self.info.http_client.post("/info", data.clone())
+            })
+            .await
+            .context("Failed to fetch order by cloid")?;
+        let response: OrderStatusResponse =
+            serde_json::from_str(&response).context("Failed to deserialize response")?;
+        Ok(response.into())
     }
 
     pub async fn fetch_order_by_oid(&self, address: H160, oid: u64) -> Result<CustomOrderStatus> {
         let response = self
-            .info
-            .query_order_by_oid(address, oid)
+            .info_request("fetch_order_by_oid", LIGHT_INFO_WEIGHT, || {
+                self.info.query_order_by_oid(address, oid)
+            })
             .await
             .context("Failed to fetch order by oid")?;
 
@@ -315,11 +655,12 @@ impl HttpClient {
         Ok(order_status)
     }
 
-    // Perp positinos
+    // Perp positions
     pub async fn fetch_user_state(&self, address: H160) -> Result<UserStateResponse> {
         let response = self
-            .info
-            .user_state(address)
+            .info_request("fetch_user_state", LIGHT_INFO_WEIGHT, || {
+                self.info.user_state(address)
+            })
             .await
             .context("Failed to fetch user state")?;
         Ok(response)
@@ -328,8 +669,9 @@ impl HttpClient {
     // Spot positions
     pub async fn fetch_token_balances(&self, address: H160) -> Result<Vec<CustomUserTokenBalance>> {
         let response = self
-            .info
-            .user_token_balances(address)
+            .info_request("fetch_token_balances", LIGHT_INFO_WEIGHT, || {
+                self.info.user_token_balances(address)
+            })
             .await
             .context("Failed to fetch token balances")?;
 
@@ -343,8 +685,9 @@ impl HttpClient {
 
     pub async fn query_order_status(&self, address: H160, oid: u64) -> Result<CustomOrderStatus> {
         let response = self
-            .info
-            .query_order_by_oid(address, oid)
+            .info_request("query_order_status", LIGHT_INFO_WEIGHT, || {
+                self.info.query_order_by_oid(address, oid)
+            })
             .await
             .context("Failed to query order status")?;
 
@@ -354,8 +697,7 @@ impl HttpClient {
 
     pub async fn fetch_all_mids(&self) -> Result<HashMap<String, f64>> {
         let response = self
-            .info
-            .all_mids()
+            .info_request("fetch_all_mids", LIGHT_INFO_WEIGHT, || self.info.all_mids())
             .await
             .context("Failed to fetch all mids")?;
 
@@ -374,17 +716,80 @@ impl HttpClient {
     }
 
     pub async fn fetch_user_fills(&self, address: H160) -> Result<Vec<CustomUserFills>> {
+        let request = serde_json::json!({ "type": "userFills", "user": address });
+        let data = serde_json::to_string(&request).context("Failed to serialize request")?;
         let response = self
-            .info
-            .user_fills(address)
+            .info_request("fetch_user_fills", INFO_WEIGHT, || {
+                self.info.http_client.post("/info", data.clone())
+            })
             .await
             .context("Failed to fetch user fills")?;
+        let fills: Vec<InfoUserFill> =
+            serde_json::from_str(&response).context("Failed to deserialize response")?;
+        self.rate_limiter.consume(response_weight(fills.len(), 20));
 
         let user_fills: Vec<CustomUserFills> =
-            response.into_iter().map(CustomUserFills::from).collect();
+            fills.into_iter().map(CustomUserFills::from).collect();
         Ok(user_fills)
     }
 
+    /// Fills with `start_time <= time <= end_time` in milliseconds, oldest first.
+    ///
+    /// `userFillsByTime` returns at most 2000 fills per request, so the range is paged by
+    /// restarting from the time of the last fill. Only the 10000 most recent fills of an
+    /// account are available from the API.
+    pub async fn fetch_user_fills_by_time(
+        &self,
+        address: H160,
+        start_time: u64,
+        end_time: u64,
+    ) -> Result<Vec<CustomUserFills>> {
+        let mut fills: Vec<CustomUserFills> = Vec::new();
+        let mut seen = HashSet::new();
+        let mut page_start = start_time;
+        loop {
+            let request = serde_json::json!({
+                "type": "userFillsByTime",
+                "user": address,
+                "startTime": page_start,
+                "endTime": end_time,
+            });
+            let data = serde_json::to_string(&request).context("Failed to serialize request")?;
+            let response = self
+                .info_request("fetch_user_fills_by_time", INFO_WEIGHT, || {
+                    self.info.http_client.post("/info", data.clone())
+                })
+                .await
+                .context("Failed to fetch user fills by time")?;
+            let mut page: Vec<InfoUserFill> =
+                serde_json::from_str(&response).context("Failed to deserialize response")?;
+            self.rate_limiter.consume(response_weight(page.len(), 20));
+            page.sort_by_key(|fill| (fill.time, fill.tid));
+
+            let page_len = page.len();
+            let last_time = page.last().map(|fill| fill.time);
+            for fill in page {
+                // Pages overlap on the boundary millisecond
+                if seen.insert((fill.hash.clone(), fill.oid, fill.tid)) {
+                    fills.push(fill.into());
+                }
+            }
+
+            match last_time {
+                Some(last_time) if page_len >= USER_FILLS_PAGE_SIZE && last_time < end_time => {
+                    // A full page within one millisecond would never advance otherwise
+                    page_start = if last_time > page_start {
+                        last_time
+                    } else {
+                        last_time + 1
+                    };
+                }
+                _ => break,
+            }
+        }
+        Ok(fills)
+    }
+
     pub async fn fetch_funding_history(
         &self,
         coin: &str,
@@ -392,10 +797,14 @@ impl HttpClient {
         end_time: Option<u64>,
     ) -> Result<Vec<FundingHistoryResponse>> {
         let response = self
-            .info
-            .funding_history(coin.to_string(), start_time, end_time)
+            .info_request("fetch_funding_history", INFO_WEIGHT, || {
+                self.info
+                    .funding_history(coin.to_string(), start_time, end_time)
+            })
             .await
             .context("Failed to fetch funding history")?;
+        self.rate_limiter
+            .consume(response_weight(response.len(), 20));
 
         Ok(response)
     }
@@ -407,20 +816,27 @@ impl HttpClient {
         end_time: Option<u64>,
     ) -> Result<Vec<UserFundingResponse>> {
         let response = self
-            .info
-            .user_funding_history(address, start_time, end_time)
+            .info_request("fetch_user_funding_history", INFO_WEIGHT, || {
+                self.info
+                    .user_funding_history(address, start_time, end_time)
+            })
             .await
             .context("Failed to fetch user funding history")?;
+        self.rate_limiter
+            .consume(response_weight(response.len(), 20));
 
         Ok(response)
     }
 
     pub async fn fetch_trades(&self, coin: &str) -> Result<Vec<CustomTrade>> {
         let response = self
-            .info
-            .recent_trades(coin.to_string())
+            .info_request("fetch_trades", INFO_WEIGHT, || {
+                self.info.recent_trades(coin.to_string())
+            })
             .await
             .context("Failed to fetch trades")?;
+        self.rate_limiter
+            .consume(response_weight(response.len(), 20));
 
         let trades: Vec<CustomTrade> = response.into_iter().map(CustomTrade::from).collect();
         Ok(trades)
@@ -429,8 +845,9 @@ impl HttpClient {
 
     pub async fn fetch_l2_book(&self, coin: &str) -> Result<CustomL2Book> {
         let response = self
-            .info
-            .l2_snapshot(coin.to_string())
+            .info_request("fetch_l2_book", LIGHT_INFO_WEIGHT, || {
+                self.info.l2_snapshot(coin.to_string())
+            })
             .await
             .context("Failed to fetch l2 book")?;
 
@@ -447,10 +864,18 @@ impl HttpClient {
         end_time: u64,
     ) -> Result<Vec<CustomCandle>> {
         let resposne = self
-            .info
-            .candles_snapshot(coin.to_string(), interval.to_string(), start_time, end_time)
+            .info_request("fetch_candles", INFO_WEIGHT, || {
+                self.info.candles_snapshot(
+                    coin.to_string(),
+                    interval.to_string(),
+                    start_time,
+                    end_time,
+                )
+            })
             .await
             .context("Failed to fetch candles")?;
+        self.rate_limiter
+            .consume(response_weight(resposne.len(), 60));
 
         let candles: Vec<CustomCandle> = resposne.into_iter().map(CustomCandle::from).collect();
         Ok(candles)
@@ -460,14 +885,92 @@ impl HttpClient {
         let request = serde_json::json!({"type": "tokenDetails", "tokenId": token_id});
         let data = serde_json::to_string(&request).context("Failed to serialize request")?;
         let response = self
-            .info
-            .http_client
-            .post("/info", data)
+            .info_request("fetch_token_details", INFO_WEIGHT, || {
+                self.info.http_client.post("/info", data.clone())
+            })
             .await
             .context("Failed to fetch token details")?;
         debug!("Token details: {:#?}", response);
         serde_json::from_str(&response).context("Failed to deserialize response")
     }
+
+    // Info requests the SDK has no method or no complete response type for
+    async fn post_info<T: DeserializeOwned>(&self, method: &str, request: Value) -> Result<T> {
+        let data = serde_json::to_string(&request).context("Failed to serialize request")?;
+        let response = self
+            .info_request(method, INFO_WEIGHT, || {
+                self.info.http_client.post("/info", data.clone())
+            })
+            .await?;
+        serde_json::from_str(&response).context("Failed to deserialize response")
+    }
+}
+
+fn side_code(is_buy: bool) -> String {
+    if is_buy { "B" } else { "A" }.to_string()
+}
+
+// Status of the order once the exchange answered, and its oid if the answer has one
+fn order_status<E>(response: &Result<ExchangeResponseStatus, E>) -> (&'static str, Option<u64>) {
+    let status = match response {
+        Err(_) => return ("error", None),
+        Ok(ExchangeResponseStatus::Err(_)) => return ("rejected", None),
+        Ok(ExchangeResponseStatus::Ok(response)) => response
+            .data
+            .as_ref()
+            .and_then(|data| data.statuses.first()),
+    };
+    match status {
+        Some(ExchangeDataStatus::Filled(order)) => ("filled", Some(order.oid)),
+        Some(ExchangeDataStatus::Resting(order)) => ("resting", Some(order.oid)),
+        Some(ExchangeDataStatus::Success) => ("canceled", None),
+        Some(ExchangeDataStatus::WaitingForFill | ExchangeDataStatus::WaitingForTrigger) => {
+            ("waiting", None)
+        }
+        Some(ExchangeDataStatus::Error(_)) | None => ("rejected", None),
+    }
+}
+
+fn order_id_from_response(response_status: ExchangeResponseStatus) -> Result<u64> {
+    match response_status {
+        ExchangeResponseStatus::Ok(exchange_response) => {
+            let oid = exchange_response
+                .data
+                .and_then(|data| {
+                    data.statuses.first().map(|status| match status {
+                        ExchangeDataStatus::Filled(order) => Some(order.oid),
+                        ExchangeDataStatus::Resting(order) => Some(order.oid),
+                        _ => None,
+                    })
+                })
+                .flatten()
+                .context("No valid statuses or unexpected status in exchange response.")?;
+            Ok(oid)
+        }
+        ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
+    }
+}
+
+fn cancel_result_from_response(response_status: ExchangeResponseStatus) -> Result<String> {
+    match response_status {
+        ExchangeResponseStatus::Ok(exchange_response) => {
+            if let Some(data) = exchange_response.data {
+                let success = data
+                    .statuses
+                    .iter()
+                    .any(|status| matches!(status, ExchangeDataStatus::Success));
+
+                if success {
+                    let success_msg = "Order cancelled successfully".to_string();
+                    return Ok(success_msg);
+                }
+            }
+            Err(anyhow!(
+                "Unexpected response format: No success status found."
+            ))
+        }
+        ExchangeResponseStatus::Err(err) => Err(anyhow!("Exchange returned an error: {}", err)),
+    }
 }
 
 fn round_to_decimals(value: f64, decimals: u32) -> f64 {
@@ -475,6 +978,12 @@ fn round_to_decimals(value: f64, decimals: u32) -> f64 {
     (value * factor).round() / factor
 }
 
+// Rounds towards zero so a size never exceeds the balance it comes from
+fn floor_to_decimals(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).floor() / factor
+}
+
 fn round_to_significant_and_decimal(value: f64, sig_figs: u32, max_decimals: u32) -> f64 {
     let abs_value = value.abs();
     let magnitude = abs_value.log10().floor() as i32;
@@ -0,0 +1,329 @@
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Spot assets are addressed as 10000 + their index in the spot universe
+pub const SPOT_INDEX_OFFSET: usize = 10000;
+
+/// `meta` info response. Unlike the SDK's `Meta` it keeps the leverage fields.
+#[derive(Debug, Deserialize)]
+pub struct PerpMetaResponse {
+    pub universe: Vec<PerpAssetMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PerpAssetMeta {
+    pub name: String,
+    #[serde(rename = "szDecimals")]
+    pub sz_decimals: u8,
+    #[serde(rename = "maxLeverage")]
+    pub max_leverage: u32,
+    #[serde(rename = "onlyIsolated", default)]
+    pub only_isolated: bool,
+    #[serde(rename = "isDelisted", default)]
+    pub is_delisted: bool,
+}
+
+/// `spotMeta` info response
+#[derive(Debug, Deserialize)]
+pub struct SpotMetaResponse {
+    pub universe: Vec<SpotPairMeta>,
+    pub tokens: Vec<SpotTokenMeta>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpotPairMeta {
+    pub name: String,
+    pub tokens: Vec<usize>,
+    pub index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SpotTokenMeta {
+    pub name: String,
+    #[serde(rename = "szDecimals")]
+    pub sz_decimals: u8,
+    #[serde(rename = "weiDecimals")]
+    pub wei_decimals: u8,
+    pub index: usize,
+    #[serde(rename = "tokenId")]
+    pub token_id: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpotToken {
+    pub name: String,
+    pub index: usize,
+    pub token_id: String, // Hex id, as used by tokenDetails
+    pub sz_decimals: u8,
+    pub wei_decimals: u8,
+}
+
+impl From<&SpotTokenMeta> for SpotToken {
+    fn from(token: &SpotTokenMeta) -> Self {
+        SpotToken {
+            name: token.name.clone(),
+            index: token.index,
+            token_id: token.token_id.clone(),
+            sz_decimals: token.sz_decimals,
+            wei_decimals: token.wei_decimals,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetMetadata {
+    pub name: String,          // "BTC" or "HYPE/USDC"
+    pub internal_name: String, // Name in fills and websocket data, "BTC" or "@107"
+    pub index: usize,          // Asset id of orders
+    pub sz_decimals: u8,
+    pub price_decimals: u32,
+    pub max_leverage: Option<u32>, // Perps only
+    pub only_isolated: bool,
+    pub is_delisted: bool,
+    pub base_token: Option<SpotToken>, // Spot only
+    pub quote_token: Option<SpotToken>,
+}
+
+impl AssetMetadata {
+    pub fn is_spot(&self) -> bool {
+        self.index >= SPOT_INDEX_OFFSET
+    }
+
+    /// Smallest price step at `price`. Prices have at most 5 significant figures and
+    /// `price_decimals` decimals, integer prices are always valid.
+    pub fn tick_size(&self, price: f64) -> f64 {
+        let decimal_tick = 10f64.powi(-(self.price_decimals as i32));
+        if price <= 0.0 || !price.is_finite() {
+            return decimal_tick;
+        }
+        let significant_tick = 10f64.powi(price.log10().floor() as i32 - 4);
+        significant_tick.max(decimal_tick).min(1.0)
+    }
+}
+
+/// Every listed perp and spot pair, looked up by display name, internal name or index
+#[derive(Debug, Default)]
+pub struct AssetMetadataMap {
+    assets: Vec<Arc<AssetMetadata>>,
+    by_name: HashMap<String, usize>,
+    by_internal_name: HashMap<String, usize>,
+    by_index: HashMap<usize, usize>,
+}
+
+impl AssetMetadataMap {
+    pub fn build(perp_meta: &PerpMetaResponse, spot_meta: &SpotMetaResponse) -> Self {
+        let mut map = AssetMetadataMap::default();
+
+        for (index, meta) in perp_meta.universe.iter().enumerate() {
+            map.insert(AssetMetadata {
+                name: meta.name.clone(),
+                internal_name: meta.name.clone(),
+                index,
+                sz_decimals: meta.sz_decimals,
+                price_decimals: price_decimals(6, meta.sz_decimals),
+                max_leverage: Some(meta.max_leverage),
+                only_isolated: meta.only_isolated,
+                is_delisted: meta.is_delisted,
+                base_token: None,
+                quote_token: None,
+            });
+        }
+
+        let tokens: HashMap<usize, &SpotTokenMeta> = spot_meta
+            .tokens
+            .iter()
+            .map(|token| (token.index, token))
+            .collect();
+        for pair in &spot_meta.universe {
+            let [base, quote] = pair.tokens[..] else {
+                continue;
+            };
+            let (Some(base), Some(quote)) = (tokens.get(&base), tokens.get(&quote)) else {
+                continue;
+            };
+            map.insert(AssetMetadata {
+                name: format!("{}/{}", base.name, quote.name),
+                internal_name: pair.name.clone(),
+                index: pair.index + SPOT_INDEX_OFFSET,
+                sz_decimals: base.sz_decimals,
+                price_decimals: price_decimals(8, base.sz_decimals),
+                max_leverage: None,
+                only_isolated: false,
+                is_delisted: false,
+                base_token: Some(SpotToken::from(*base)),
+                quote_token: Some(SpotToken::from(*quote)),
+            });
+        }
+
+        map
+    }
+
+    fn insert(&mut self, asset: AssetMetadata) {
+        let position = self.assets.len();
+        self.by_name.insert(asset.name.clone(), position);
+        self.by_internal_name
+            .insert(asset.internal_name.clone(), position);
+        self.by_index.insert(asset.index, position);
+        self.assets.push(Arc::new(asset));
+    }
+
+    /// `key` is a display name ("HYPE/USDC"), an internal name ("@107") or an asset index ("10107")
+    pub fn get(&self, key: &str) -> Option<Arc<AssetMetadata>> {
+        self.by_name
+            .get(key)
+            .or_else(|| self.by_internal_name.get(key))
+            .or_else(|| {
+                key.parse::<usize>()
+                    .ok()
+                    .and_then(|index| self.by_index.get(&index))
+            })
+            .map(|position| self.assets[*position].clone())
+    }
+
+    pub fn get_by_index(&self, index: usize) -> Option<Arc<AssetMetadata>> {
+        self.by_index
+            .get(&index)
+            .map(|position| self.assets[*position].clone())
+    }
+
+    pub fn get_by_internal_name(&self, internal_name: &str) -> Option<Arc<AssetMetadata>> {
+        self.by_internal_name
+            .get(internal_name)
+            .map(|position| self.assets[*position].clone())
+    }
+
+    /// Like `get`, with an error that says what was expected
+    pub fn resolve(&self, key: &str) -> Result<Arc<AssetMetadata>> {
+        self.get(key).ok_or_else(|| {
+            anyhow!(
+                "Unknown coin {:?}: expected a perp (e.g. BTC), a spot pair (e.g. HYPE/USDC), \
+                 an internal name (e.g. @107) or an asset index",
+                key
+            )
+        })
+    }
+
+    pub fn assets(&self) -> &[Arc<AssetMetadata>] {
+        &self.assets
+    }
+
+    pub fn len(&self) -> usize {
+        self.assets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.assets.is_empty()
+    }
+}
+
+// Perp prices have at most 6 decimals minus szDecimals, spot prices 8
+fn price_decimals(max_decimals: u32, sz_decimals: u8) -> u32 {
+    max_decimals.saturating_sub(sz_decimals as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> AssetMetadataMap {
+        let perp_meta: PerpMetaResponse = serde_json::from_str(
+            r#"{"universe": [
+                {"name": "BTC", "szDecimals": 5, "maxLeverage": 40},
+                {"name": "ETH", "szDecimals": 4, "maxLeverage": 25},
+                {"name": "kPEPE", "szDecimals": 0, "maxLeverage": 10, "onlyIsolated": true}
+            ]}"#,
+        )
+        .unwrap();
+        let spot_meta: SpotMetaResponse = serde_json::from_str(
+            r#"{"universe": [
+                {"name": "@107", "tokens": [150, 0], "index": 107},
+                {"name": "@999", "tokens": [150, 404], "index": 999}
+            ], "tokens": [
+                {"name": "USDC", "szDecimals": 8, "weiDecimals": 8, "index": 0, "tokenId": "0x6d1e"},
+                {"name": "HYPE", "szDecimals": 2, "weiDecimals": 8, "index": 150, "tokenId": "0x0d01"}
+            ]}"#,
+        )
+        .unwrap();
+        AssetMetadataMap::build(&perp_meta, &spot_meta)
+    }
+
+    fn assert_tick(asset: &AssetMetadata, price: f64, expected: f64) {
+        let tick = asset.tick_size(price);
+        assert!(
+            (tick - expected).abs() < expected * 1e-9,
+            "tick at {} is {}, expected {}",
+            price,
+            tick,
+            expected
+        );
+    }
+
+    #[test]
+    fn builds_perps_and_spot_pairs() {
+        let map = map();
+        // The pair with an unknown token is skipped
+        assert_eq!(map.len(), 4);
+
+        let btc = map.resolve("BTC").unwrap();
+        assert_eq!(btc.index, 0);
+        assert_eq!(btc.price_decimals, 1);
+        assert_eq!(btc.max_leverage, Some(40));
+        assert!(!btc.is_spot());
+        assert!(map.resolve("kPEPE").unwrap().only_isolated);
+
+        let hype = map.resolve("HYPE/USDC").unwrap();
+        assert_eq!(hype.internal_name, "@107");
+        assert_eq!(hype.index, 107 + SPOT_INDEX_OFFSET);
+        assert_eq!(hype.price_decimals, 6);
+        assert_eq!(hype.max_leverage, None);
+        assert_eq!(hype.base_token.as_ref().unwrap().name, "HYPE");
+        assert_eq!(hype.quote_token.as_ref().unwrap().token_id, "0x6d1e");
+        assert!(hype.is_spot());
+    }
+
+    #[test]
+    fn resolves_display_name_internal_name_and_index() {
+        let map = map();
+        let hype = map.resolve("HYPE/USDC").unwrap();
+        assert_eq!(map.resolve("@107").unwrap(), hype);
+        assert_eq!(map.resolve("10107").unwrap(), hype);
+        assert_eq!(map.get_by_index(10107).unwrap(), hype);
+        assert_eq!(map.get_by_internal_name("@107").unwrap(), hype);
+        assert_eq!(map.resolve("1").unwrap().name, "ETH");
+    }
+
+    #[test]
+    fn unknown_coin_is_an_error() {
+        let map = map();
+        for key in ["DOGE", "@999", "5", "btc"] {
+            let error = map.resolve(key).unwrap_err().to_string();
+            assert!(error.contains(&format!("{:?}", key)), "{}", error);
+            assert!(error.contains("HYPE/USDC"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn tick_size_uses_significant_figures_and_decimals() {
+        let map = map();
+        let btc = map.resolve("BTC").unwrap();
+        assert_tick(&btc, 65_432.0, 1.0);
+        assert_tick(&btc, 250_000.0, 1.0); // Integer prices are always valid
+        assert_tick(&btc, 1_234.5, 0.1); // Capped by price_decimals
+        assert_tick(&btc, 0.0, 0.1);
+        assert_tick(&btc, f64::NAN, 0.1);
+
+        let eth = map.resolve("ETH").unwrap();
+        assert_tick(&eth, 3_456.7, 0.1);
+        assert_tick(&eth, 123.45, 0.01);
+
+        let kpepe = map.resolve("kPEPE").unwrap();
+        assert_tick(&kpepe, 0.012345, 0.000001);
+
+        let hype = map.resolve("HYPE/USDC").unwrap();
+        assert_tick(&hype, 12.345, 0.001);
+        assert_tick(&hype, 1.2345, 0.0001);
+        assert_tick(&hype, 0.00012345, 0.000001);
+    }
+}
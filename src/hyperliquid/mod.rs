@@ -1,7 +1,18 @@
+pub mod backfill;
+pub mod chart;
+pub mod events;
+pub mod fill_log;
+pub mod fill_writer;
 pub mod http;
+pub mod metadata;
 pub mod model;
 pub mod order;
+pub mod order_audit;
 pub mod portfolio;
+pub mod rate_limit;
+pub mod retry;
+pub mod report;
+pub mod ring_buffer;
 pub mod subscriptions;
 pub mod websocket;
 pub mod db;
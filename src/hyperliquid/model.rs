@@ -1,7 +1,6 @@
 use hyperliquid_rust_sdk::{
     CandleData, CandlesSnapshotResponse, L2BookData, L2SnapshotResponse, OpenOrdersResponse,
-    OrderStatusResponse, RecentTradesResponse, Trade, TradeInfo, UserFillsResponse,
-    UserTokenBalance,
+    OrderStatusResponse, RecentTradesResponse, Trade, TradeInfo, UserTokenBalance,
 };
 use serde::de::{self, SeqAccess, Visitor};
 use serde::{Deserialize, Deserializer, Serialize};
@@ -211,23 +210,50 @@ pub struct CustomUserFills {
     pub size: f64,
     pub timestamp: i64,
     pub fee: f64,
+    #[serde(default)] // Missing in fill logs written before tid was recorded
+    pub tid: i64,
 }
 
-impl From<UserFillsResponse> for CustomUserFills {
-    fn from(fills: UserFillsResponse) -> Self {
+/// One fill of the `userFills` and `userFillsByTime` info responses. The SDK's
+/// `UserFillsResponse` drops the tid, so fills are requested and parsed here.
+#[derive(Debug, Deserialize)]
+pub struct InfoUserFill {
+    #[serde(rename = "closedPnl", deserialize_with = "string_to_f64")]
+    pub closed_pnl: f64,
+    pub coin: String,
+    pub crossed: bool,
+    pub dir: String,
+    pub hash: String,
+    pub oid: u64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub px: f64,
+    pub side: String,
+    #[serde(rename = "startPosition", deserialize_with = "string_to_f64")]
+    pub start_position: f64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub sz: f64,
+    pub time: u64,
+    #[serde(deserialize_with = "string_to_f64")]
+    pub fee: f64,
+    pub tid: u64,
+}
+
+impl From<InfoUserFill> for CustomUserFills {
+    fn from(fill: InfoUserFill) -> Self {
         CustomUserFills {
-            closed_pnl: fills.closed_pnl.parse().unwrap_or(0.0), // Convert the "closed_pnl" field from string to f64
-            coin: fills.coin,
-            crossed: fills.crossed,
-            dir: fills.dir,
-            hash: fills.hash,
-            order_id: fills.oid as i64,
-            price: fills.px.parse().unwrap_or(0.0), // Convert the "price" field from string to f64
-            side: fills.side,
-            start_position: fills.start_position.parse().unwrap_or(0.0), // Convert the "start_position" field from string to f64
-            size: fills.sz.parse().unwrap_or(0.0), // Convert the "size" field from string to f64
-            timestamp: fills.time as i64,
-            fee: fills.fee.parse().unwrap_or(0.0), // Convert the "fee" field from string to f64
+            closed_pnl: fill.closed_pnl,
+            coin: fill.coin,
+            crossed: fill.crossed,
+            dir: fill.dir,
+            hash: fill.hash,
+            order_id: fill.oid as i64,
+            price: fill.px,
+            side: fill.side,
+            start_position: fill.start_position,
+            size: fill.sz,
+            timestamp: fill.time as i64,
+            fee: fill.fee,
+            tid: fill.tid as i64,
         }
     }
 }
@@ -337,6 +363,7 @@ impl From<TradeInfo> for CustomUserFills {
             size: fills.sz.parse().unwrap_or(0.0), // Convert the "size" field from string to f64
             timestamp: fills.time as i64,
             fee: fills.fee.parse().unwrap_or(0.0), // Convert the "fee" field from string to f64
+            tid: fills.tid as i64,
         }
     }
 }
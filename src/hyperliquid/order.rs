@@ -45,6 +45,7 @@ pub struct MarketOrderParams {
     pub asset: String,
     pub is_buy: bool,
     pub size: f64,
+    pub reduce_only: Option<bool>,
     pub cloid: Option<Uuid>,
 }
 impl MarketOrderParams {
@@ -53,9 +54,14 @@ impl MarketOrderParams {
             asset,
             is_buy,
             size,
+            reduce_only: None,
             cloid: None,
         }
     }
+    pub fn reduce_only(mut self, value: bool) -> Self {
+        self.reduce_only = Some(value);
+        self
+    }
     pub fn cloid(mut self, value: Uuid) -> Self {
         self.cloid = Some(value);
         self
@@ -0,0 +1,191 @@
+use crate::hyperliquid::db::Storage;
+use anyhow::{Context, Result};
+use ethers::types::H160;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// One exchange call made by `HttpClient` and what came back
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderRequestRecord {
+    pub action: String, // limit_order, market_order, cancel_order or cancel_by_cloid
+    pub coin: String,
+    pub order_id: Option<i64>,
+    pub cloid: Option<String>,
+    pub side: Option<String>, // "B" or "A", placements only
+    pub price: Option<f64>,
+    pub size: Option<f64>,
+    pub order_type: Option<String>, // Time in force, e.g. Gtc or Ioc
+    pub reduce_only: bool,
+    pub status: String, // resting, filled, canceled, rejected or error
+    pub response: String,
+    pub sent_at: i64, // Milliseconds
+    pub latency_ms: i64,
+}
+
+impl OrderRequestRecord {
+    pub fn new(action: &str, coin: &str) -> Self {
+        Self {
+            action: action.to_string(),
+            coin: coin.to_string(),
+            order_id: None,
+            cloid: None,
+            side: None,
+            price: None,
+            size: None,
+            order_type: None,
+            reduce_only: false,
+            status: String::new(),
+            response: String::new(),
+            sent_at: 0,
+            latency_ms: 0,
+        }
+    }
+
+    pub fn is_placement(&self) -> bool {
+        self.side.is_some()
+    }
+
+    /// Status the request was sent with, before the response came back
+    pub fn sent_status(&self) -> &'static str {
+        if self.is_placement() {
+            "sent"
+        } else {
+            "cancel_sent"
+        }
+    }
+}
+
+/// An entry of `order_status_transitions`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OrderStatusTransition {
+    pub order_id: Option<i64>,
+    pub cloid: Option<String>,
+    pub status: String,
+    pub message: Option<String>,
+    pub timestamp: i64,
+}
+
+/// Filters audit records, everything of the user by default
+#[derive(Debug, Clone, Default)]
+pub struct OrderAuditQuery {
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    pub cloid: Option<String>,
+}
+
+impl OrderAuditQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `start_time <= sent_at <= end_time`, in milliseconds
+    pub fn time_range(mut self, start_time: i64, end_time: i64) -> Self {
+        self.start_time = Some(start_time);
+        self.end_time = Some(end_time);
+        self
+    }
+
+    pub fn cloid(mut self, value: String) -> Self {
+        self.cloid = Some(value);
+        self
+    }
+
+    pub fn matches(&self, timestamp: i64, cloid: Option<&str>) -> bool {
+        self.start_time.is_none_or(|start| timestamp >= start)
+            && self.end_time.is_none_or(|end| timestamp <= end)
+            && self
+                .cloid
+                .as_deref()
+                .is_none_or(|wanted| cloid == Some(wanted))
+    }
+}
+
+pub fn order_audit_path(user: H160) -> PathBuf {
+    PathBuf::from(format!("{:?}_orders.log", user))
+}
+
+/// Persists order records in the background so the audit trail adds no latency to orders
+#[derive(Clone)]
+pub struct OrderAuditor {
+    sender: UnboundedSender<OrderRequestRecord>,
+}
+
+impl OrderAuditor {
+    /// Records go to the database, or to `{user:?}_orders.log` without one or while
+    /// the database fails
+    pub fn spawn(storage: Option<Arc<dyn Storage>>, user: H160) -> Self {
+        let (sender, receiver) = unbounded_channel();
+        tokio::spawn(run_order_auditor(receiver, storage, user));
+        Self { sender }
+    }
+
+    pub fn record(&self, record: OrderRequestRecord) {
+        if self.sender.send(record).is_err() {
+            error!("Order auditor stopped, order request was not recorded");
+        }
+    }
+}
+
+async fn run_order_auditor(
+    mut receiver: UnboundedReceiver<OrderRequestRecord>,
+    storage: Option<Arc<dyn Storage>>,
+    user: H160,
+) {
+    while let Some(record) = receiver.recv().await {
+        if let Some(storage) = &storage {
+            match storage.save_order_request(&record, user).await {
+                Ok(()) => continue,
+                Err(e) => warn!("Failed to save order request, writing it to file: {:?}", e),
+            }
+        }
+        if let Err(e) = append_order_request(&order_audit_path(user), &record) {
+            error!("Failed to record order request {:?}: {:?}", record, e);
+        }
+    }
+}
+
+fn append_order_request(path: &Path, record: &OrderRequestRecord) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context("Failed to open order audit file")?;
+    let json = serde_json::to_string(record).context("Failed to serialize order request")?;
+    writeln!(file, "{}", json).context("Failed to write to order audit file")?;
+    Ok(())
+}
+
+/// Reads a `{user:?}_orders.log` NDJSON file. Lines that fail to parse are skipped with a warning.
+pub fn load_order_requests_from_log(
+    path: &Path,
+    query: &OrderAuditQuery,
+) -> Result<Vec<OrderRequestRecord>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+
+    let mut records = Vec::new();
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<OrderRequestRecord>(&line) {
+            Ok(record) if query.matches(record.sent_at, record.cloid.as_deref()) => {
+                records.push(record)
+            }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "Skipping invalid line {} of {}: {}",
+                index + 1,
+                path.display(),
+                e
+            ),
+        }
+    }
+    records.sort_by_key(|record| record.sent_at);
+    Ok(records)
+}
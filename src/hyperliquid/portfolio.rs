@@ -33,6 +33,18 @@ impl PortfolioManager {
         }
     }
 
+    /// Rebuilds positions offline by replaying fills in time order
+    pub fn from_fills(fills: &[CustomUserFills]) -> Self {
+        let mut sorted: Vec<&CustomUserFills> = fills.iter().collect();
+        sorted.sort_by_key(|fill| (fill.timestamp, fill.tid));
+
+        let mut portfolio = Self::new();
+        for fill in sorted {
+            portfolio.update_position(fill);
+        }
+        portfolio
+    }
+
     pub fn update_position(&mut self, fill: &CustomUserFills) {
         let position = self.positions.entry(fill.coin.clone()).or_insert(Position {
             coin: fill.coin.clone(),
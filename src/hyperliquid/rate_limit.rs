@@ -0,0 +1,288 @@
+use crate::utils::metrics::{RATE_LIMITED, RATE_LIMIT_REMAINING};
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use serde::Deserialize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Weights from https://hyperliquid.gitbook.io/hyperliquid-docs/for-developers/api/rate-limits-and-user-limits
+pub const EXCHANGE_WEIGHT: u32 = 1; // Per action with a single order or cancel
+pub const LIGHT_INFO_WEIGHT: u32 = 2; // l2Book, allMids, clearinghouseState, orderStatus, spotClearinghouseState
+pub const INFO_WEIGHT: u32 = 20; // Every other info request
+
+/// `[rate_limit]` section of the bot config. The exchange allows a total request weight
+/// of 1200 per minute per IP, shared by every bot running on the same host.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    #[serde(default = "default_weight_per_minute")]
+    pub weight_per_minute: u32,
+    #[serde(default = "default_cancel_reserve")]
+    pub cancel_reserve: u32, // Weight only cancels may use, so orders can always be pulled
+    #[serde(default = "default_max_wait_ms")]
+    pub max_wait_ms: u64, // Requests that would wait longer fail instead, 0 never waits
+}
+
+fn default_weight_per_minute() -> u32 {
+    1200
+}
+
+fn default_cancel_reserve() -> u32 {
+    100
+}
+
+fn default_max_wait_ms() -> u64 {
+    10_000
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            weight_per_minute: default_weight_per_minute(),
+            cancel_reserve: default_cancel_reserve(),
+            max_wait_ms: default_max_wait_ms(),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.weight_per_minute == 0 {
+            errors.push("rate_limit.weight_per_minute: must be greater than 0".to_string());
+        }
+        if self.cancel_reserve >= self.weight_per_minute {
+            errors
+                .push("rate_limit.cancel_reserve: must be less than weight_per_minute".to_string());
+        }
+        errors
+    }
+}
+
+/// Cancels may use the whole budget, everything else has to leave `cancel_reserve`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestPriority {
+    Cancel,
+    Order,
+    Info,
+}
+
+impl RequestPriority {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RequestPriority::Cancel => "cancel",
+            RequestPriority::Order => "order",
+            RequestPriority::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitStatus {
+    pub remaining: f64, // Weight available right now, including the cancel reserve
+    pub capacity: u32,
+    pub cancel_reserve: u32,
+}
+
+impl RateLimitStatus {
+    /// Weight left for orders and info requests
+    pub fn available(&self) -> f64 {
+        (self.remaining - self.cancel_reserve as f64).max(0.0)
+    }
+}
+
+/// Token bucket over the request weight, refilled continuously at `weight_per_minute`
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    bucket: Mutex<Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let bucket = Bucket {
+            tokens: config.weight_per_minute as f64,
+            updated_at: Instant::now(),
+        };
+        Self {
+            config,
+            bucket: Mutex::new(bucket),
+        }
+    }
+
+    /// Waits until `weight` fits into the budget of `priority`, or fails when that
+    /// would take longer than `max_wait_ms`
+    pub async fn acquire(&self, weight: u32, priority: RequestPriority) -> Result<()> {
+        let deadline = Instant::now() + Duration::from_millis(self.config.max_wait_ms);
+        loop {
+            let wait = match self.try_acquire(weight, priority) {
+                None => return Ok(()),
+                Some(wait) => wait,
+            };
+            if Instant::now() + wait > deadline {
+                RATE_LIMITED.with_label_values(&[priority.label()]).inc();
+                let status = self.status();
+                warn!(
+                    "Rate limit budget exhausted for {} request of weight {}, {:.0} of {} left",
+                    priority.label(),
+                    weight,
+                    status.remaining,
+                    status.capacity
+                );
+                bail!(
+                    "Rate limit budget exhausted: {} request of weight {} would wait {}ms",
+                    priority.label(),
+                    weight,
+                    wait.as_millis()
+                );
+            }
+            debug!(
+                "Waiting {}ms for rate limit budget ({} weight {})",
+                wait.as_millis(),
+                priority.label(),
+                weight
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Charges weight that is only known from the response, e.g. per 20 fills returned.
+    /// The budget may go negative, later requests wait for it to recover.
+    pub fn consume(&self, weight: u32) {
+        let mut bucket = self.lock();
+        self.refill(&mut bucket);
+        bucket.tokens -= weight as f64;
+        RATE_LIMIT_REMAINING.set(bucket.tokens);
+    }
+
+    pub fn status(&self) -> RateLimitStatus {
+        let mut bucket = self.lock();
+        self.refill(&mut bucket);
+        RateLimitStatus {
+            remaining: bucket.tokens,
+            capacity: self.config.weight_per_minute,
+            cancel_reserve: self.config.cancel_reserve,
+        }
+    }
+
+    // None when the weight was taken, otherwise how long until it would fit
+    fn try_acquire(&self, weight: u32, priority: RequestPriority) -> Option<Duration> {
+        let mut bucket = self.lock();
+        self.refill(&mut bucket);
+        let reserve = match priority {
+            RequestPriority::Cancel => 0.0,
+            RequestPriority::Order | RequestPriority::Info => self.config.cancel_reserve as f64,
+        };
+        let missing = weight as f64 + reserve - bucket.tokens;
+        if missing <= 0.0 {
+            bucket.tokens -= weight as f64;
+            RATE_LIMIT_REMAINING.set(bucket.tokens);
+            return None;
+        }
+        Some(Duration::from_secs_f64(missing / self.refill_per_second()))
+    }
+
+    fn refill(&self, bucket: &mut Bucket) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second())
+            .min(self.config.weight_per_minute as f64);
+        bucket.updated_at = now;
+    }
+
+    fn refill_per_second(&self) -> f64 {
+        self.config.weight_per_minute as f64 / 60.0
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        self.bucket.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Extra weight of info requests that grows with the response, one per `per` items
+pub fn response_weight(items: usize, per: usize) -> u32 {
+    (items / per) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(weight_per_minute: u32, cancel_reserve: u32, max_wait_ms: u64) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            weight_per_minute,
+            cancel_reserve,
+            max_wait_ms,
+        })
+    }
+
+    #[tokio::test]
+    async fn orders_leave_the_cancel_reserve() {
+        let limiter = limiter(100, 10, 0);
+        limiter.acquire(90, RequestPriority::Order).await.unwrap();
+        assert!(limiter.acquire(1, RequestPriority::Order).await.is_err());
+        assert!(limiter.acquire(1, RequestPriority::Info).await.is_err());
+        limiter.acquire(10, RequestPriority::Cancel).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn reserved_cancel_after_the_bucket_is_drained() {
+        let limiter = limiter(100, 10, 0);
+        limiter.acquire(90, RequestPriority::Order).await.unwrap();
+        limiter.acquire(10, RequestPriority::Cancel).await.unwrap();
+        assert!(limiter.acquire(1, RequestPriority::Cancel).await.is_err());
+        assert!(limiter.status().available() < 1.0);
+    }
+
+    #[tokio::test]
+    async fn consume_may_go_negative() {
+        let limiter = limiter(100, 10, 0);
+        limiter.acquire(50, RequestPriority::Info).await.unwrap();
+        limiter.consume(80);
+        let status = limiter.status();
+        assert!(status.remaining < -29.0);
+        assert_eq!(status.available(), 0.0);
+        assert!(limiter.acquire(1, RequestPriority::Cancel).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn waits_for_the_refill_within_max_wait() {
+        // 100 weight per second
+        let limiter = limiter(6000, 0, 1000);
+        limiter.acquire(6000, RequestPriority::Order).await.unwrap();
+        let started = Instant::now();
+        limiter.acquire(10, RequestPriority::Order).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_wait_exceeds_max_wait() {
+        let limiter = limiter(6000, 0, 50);
+        limiter.acquire(6000, RequestPriority::Order).await.unwrap();
+        let started = Instant::now();
+        assert!(limiter.acquire(100, RequestPriority::Order).await.is_err());
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn validate_rejects_a_reserve_above_capacity() {
+        assert!(RateLimitConfig::default().validate().is_empty());
+        let config = RateLimitConfig {
+            weight_per_minute: 100,
+            cancel_reserve: 100,
+            max_wait_ms: 0,
+        };
+        assert_eq!(config.validate().len(), 1);
+    }
+
+    #[test]
+    fn response_weight_per_items() {
+        assert_eq!(response_weight(0, 20), 0);
+        assert_eq!(response_weight(19, 20), 0);
+        assert_eq!(response_weight(45, 20), 2);
+    }
+}
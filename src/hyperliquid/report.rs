@@ -0,0 +1,522 @@
+use crate::hyperliquid::model::CustomUserFills;
+use chrono::{Duration, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+const HOUR_MS: i64 = 60 * 60 * 1000;
+// Crypto trades every day of the year
+const PERIODS_PER_YEAR: f64 = 365.0;
+
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    /// Account value before the first fill. Returns for Sharpe/Sortino and the drawdown
+    /// percentage are relative to it; with 0 they are computed on raw PnL instead.
+    pub initial_equity: f64,
+    /// Timezone the day and hour buckets are cut in
+    pub utc_offset: FixedOffset,
+}
+
+impl Default for ReportOptions {
+    fn default() -> Self {
+        Self {
+            initial_equity: 0.0,
+            utc_offset: FixedOffset::east_opt(0).unwrap(),
+        }
+    }
+}
+
+impl ReportOptions {
+    pub fn initial_equity(mut self, initial_equity: f64) -> Self {
+        self.initial_equity = initial_equity;
+        self
+    }
+
+    pub fn utc_offset(mut self, utc_offset: FixedOffset) -> Self {
+        self.utc_offset = utc_offset;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PnlBreakdown {
+    pub realized_pnl: f64,
+    pub fees: f64,
+    /// realized_pnl - fees
+    pub net_pnl: f64,
+    pub volume: f64,
+    pub fills: usize,
+}
+
+impl PnlBreakdown {
+    fn add(&mut self, fill: &CustomUserFills) {
+        let fee = fee_in_quote(fill);
+        self.realized_pnl += fill.closed_pnl;
+        self.fees += fee;
+        self.net_pnl += fill.closed_pnl - fee;
+        self.volume += fill.price * fill.size;
+        self.fills += 1;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodPnl {
+    /// Start of the day or hour in milliseconds
+    pub start_time: i64,
+    #[serde(flatten)]
+    pub pnl: PnlBreakdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinPnl {
+    pub coin: String,
+    #[serde(flatten)]
+    pub pnl: PnlBreakdown,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: i64,
+    pub equity: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TradeStats {
+    /// Fills that realized PnL, i.e. closed or reduced a position
+    pub closing_fills: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub win_rate: Option<f64>,
+    pub average_win: Option<f64>,
+    /// Negative, the mean of the losing fills
+    pub average_loss: Option<f64>,
+    /// Gross wins over gross losses, None without losses
+    pub profit_factor: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionStats {
+    pub maker_fills: usize,
+    pub taker_fills: usize,
+    pub maker_volume: f64,
+    pub taker_volume: f64,
+    pub maker_fees: f64,
+    pub taker_fees: f64,
+    /// Share of the volume that added liquidity
+    pub maker_ratio: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PnlReport {
+    pub start_time: i64,
+    pub end_time: i64,
+    pub total: PnlBreakdown,
+    pub by_day: Vec<PeriodPnl>,
+    pub by_hour: Vec<PeriodPnl>,
+    pub by_coin: Vec<CoinPnl>,
+    pub equity_curve: Vec<EquityPoint>,
+    pub max_drawdown: f64,
+    /// max_drawdown relative to the peak equity, None without initial_equity
+    pub max_drawdown_pct: Option<f64>,
+    /// Longest time spent below a previous equity peak
+    pub max_drawdown_duration_ms: i64,
+    /// Annualized from daily returns
+    pub sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    pub trades: TradeStats,
+    pub execution: ExecutionStats,
+    /// Traded volume relative to initial_equity, None without it
+    pub turnover: Option<f64>,
+}
+
+impl PnlReport {
+    /// Builds the report from a fill history in any order, e.g. the result of
+    /// `load_fills_from_db_with_time_filter` or the fills kept by `WsData`.
+    pub fn from_fills(fills: &[CustomUserFills], options: &ReportOptions) -> Self {
+        let mut sorted: Vec<&CustomUserFills> = fills.iter().collect();
+        sorted.sort_by_key(|fill| (fill.timestamp, fill.tid));
+
+        let start_time = sorted.first().map_or(0, |fill| fill.timestamp);
+        let end_time = sorted.last().map_or(0, |fill| fill.timestamp);
+
+        let mut total = PnlBreakdown::default();
+        let mut by_day: BTreeMap<i64, PnlBreakdown> = BTreeMap::new();
+        let mut by_hour: BTreeMap<i64, PnlBreakdown> = BTreeMap::new();
+        let mut by_coin: BTreeMap<String, PnlBreakdown> = BTreeMap::new();
+        let mut equity_curve = Vec::with_capacity(sorted.len());
+        let mut trades = TradeStats::default();
+        let mut execution = ExecutionStats::default();
+        let (mut gross_win, mut gross_loss) = (0.0, 0.0);
+
+        let mut equity = options.initial_equity;
+        for fill in &sorted {
+            total.add(fill);
+            by_day
+                .entry(period_start(fill.timestamp, options.utc_offset, DAY_MS))
+                .or_default()
+                .add(fill);
+            by_hour
+                .entry(period_start(fill.timestamp, options.utc_offset, HOUR_MS))
+                .or_default()
+                .add(fill);
+            by_coin.entry(fill.coin.clone()).or_default().add(fill);
+
+            let fee = fee_in_quote(fill);
+            equity += fill.closed_pnl - fee;
+            equity_curve.push(EquityPoint {
+                timestamp: fill.timestamp,
+                equity,
+            });
+
+            if fill.closed_pnl != 0.0 {
+                trades.closing_fills += 1;
+                if fill.closed_pnl > 0.0 {
+                    trades.wins += 1;
+                    gross_win += fill.closed_pnl;
+                } else {
+                    trades.losses += 1;
+                    gross_loss -= fill.closed_pnl;
+                }
+            }
+
+            let notional = fill.price * fill.size;
+            if fill.crossed {
+                execution.taker_fills += 1;
+                execution.taker_volume += notional;
+                execution.taker_fees += fee;
+            } else {
+                execution.maker_fills += 1;
+                execution.maker_volume += notional;
+                execution.maker_fees += fee;
+            }
+        }
+
+        trades.win_rate = ratio(trades.wins as f64, trades.closing_fills as f64);
+        trades.average_win = ratio(gross_win, trades.wins as f64);
+        trades.average_loss = ratio(-gross_loss, trades.losses as f64);
+        trades.profit_factor = ratio(gross_win, gross_loss);
+        execution.maker_ratio = ratio(execution.maker_volume, total.volume);
+
+        let (max_drawdown, max_drawdown_pct, max_drawdown_duration_ms) =
+            drawdown(&equity_curve, options.initial_equity, end_time);
+        let daily_returns = daily_returns(&by_day, options.initial_equity);
+
+        Self {
+            start_time,
+            end_time,
+            by_day: into_periods(by_day),
+            by_hour: into_periods(by_hour),
+            by_coin: by_coin
+                .into_iter()
+                .map(|(coin, pnl)| CoinPnl { coin, pnl })
+                .collect(),
+            equity_curve,
+            max_drawdown,
+            max_drawdown_pct,
+            max_drawdown_duration_ms,
+            sharpe_ratio: sharpe_ratio(&daily_returns),
+            sortino_ratio: sortino_ratio(&daily_returns),
+            trades,
+            execution,
+            turnover: (options.initial_equity > 0.0).then(|| total.volume / options.initial_equity),
+            total,
+        }
+    }
+}
+
+impl fmt::Display for PnlReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let optional = |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.2}", v));
+        let percent =
+            |value: Option<f64>| value.map_or("-".to_string(), |v| format!("{:.1}%", v * 100.0));
+
+        writeln!(
+            f,
+            "Net PnL: {:.2} (realized {:.2}, fees {:.2})",
+            self.total.net_pnl, self.total.realized_pnl, self.total.fees
+        )?;
+        writeln!(
+            f,
+            "Fills: {}, volume: {:.2}, maker: {}",
+            self.total.fills,
+            self.total.volume,
+            percent(self.execution.maker_ratio)
+        )?;
+        writeln!(
+            f,
+            "Win rate: {}, avg win: {}, avg loss: {}, profit factor: {}",
+            percent(self.trades.win_rate),
+            optional(self.trades.average_win),
+            optional(self.trades.average_loss),
+            optional(self.trades.profit_factor)
+        )?;
+        writeln!(
+            f,
+            "Max drawdown: {:.2} ({}), {}",
+            self.max_drawdown,
+            percent(self.max_drawdown_pct),
+            format_duration(self.max_drawdown_duration_ms)
+        )?;
+        writeln!(
+            f,
+            "Sharpe: {}, Sortino: {}",
+            optional(self.sharpe_ratio),
+            optional(self.sortino_ratio)
+        )?;
+        for coin in &self.by_coin {
+            writeln!(
+                f,
+                "  {}: {:.2} ({} fills)",
+                coin.coin, coin.pnl.net_pnl, coin.pnl.fills
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fee in the quote currency. Spot buys pay the fee in the base token.
+pub fn fee_in_quote(fill: &CustomUserFills) -> f64 {
+    let is_spot = fill.coin.starts_with('@') || fill.coin.contains('/');
+    if is_spot && fill.side == "B" {
+        fill.fee * fill.price
+    } else {
+        fill.fee
+    }
+}
+
+// Start of the day or hour containing `timestamp`, cut in `utc_offset`
+fn period_start(timestamp: i64, utc_offset: FixedOffset, period_ms: i64) -> i64 {
+    let offset_ms = utc_offset.local_minus_utc() as i64 * 1000;
+    (timestamp + offset_ms).div_euclid(period_ms) * period_ms - offset_ms
+}
+
+fn into_periods(periods: BTreeMap<i64, PnlBreakdown>) -> Vec<PeriodPnl> {
+    periods
+        .into_iter()
+        .map(|(start_time, pnl)| PeriodPnl { start_time, pnl })
+        .collect()
+}
+
+fn ratio(numerator: f64, denominator: f64) -> Option<f64> {
+    (denominator != 0.0).then(|| numerator / denominator)
+}
+
+// Returns (max drawdown, max drawdown relative to its peak, longest time under water)
+fn drawdown(curve: &[EquityPoint], initial_equity: f64, end_time: i64) -> (f64, Option<f64>, i64) {
+    let mut peak = initial_equity;
+    let mut peak_time = curve.first().map_or(0, |point| point.timestamp);
+    let mut max_drawdown: f64 = 0.0;
+    let mut max_drawdown_pct: f64 = 0.0;
+    let mut max_duration = 0;
+    let mut under_water = false;
+
+    for point in curve {
+        if point.equity >= peak {
+            if under_water {
+                max_duration = max_duration.max(point.timestamp - peak_time);
+                under_water = false;
+            }
+            peak = point.equity;
+            peak_time = point.timestamp;
+        } else {
+            under_water = true;
+            let drawdown = peak - point.equity;
+            max_drawdown = max_drawdown.max(drawdown);
+            if peak > 0.0 {
+                max_drawdown_pct = max_drawdown_pct.max(drawdown / peak);
+            }
+        }
+    }
+    // Still under water at the end of the history
+    if under_water {
+        max_duration = max_duration.max(end_time - peak_time);
+    }
+
+    let max_drawdown_pct = (initial_equity > 0.0).then_some(max_drawdown_pct);
+    (max_drawdown, max_drawdown_pct, max_duration)
+}
+
+// Days without fills count as zero returns
+fn daily_returns(by_day: &BTreeMap<i64, PnlBreakdown>, initial_equity: f64) -> Vec<f64> {
+    let (Some(&first), Some(&last)) = (by_day.keys().next(), by_day.keys().next_back()) else {
+        return Vec::new();
+    };
+
+    let mut equity = initial_equity;
+    let mut returns = Vec::new();
+    let mut day = first;
+    while day <= last {
+        let pnl = by_day.get(&day).map_or(0.0, |breakdown| breakdown.net_pnl);
+        // Raw PnL without initial_equity, even once the PnL so far is positive
+        returns.push(if initial_equity > 0.0 && equity > 0.0 {
+            pnl / equity
+        } else {
+            pnl
+        });
+        equity += pnl;
+        day += DAY_MS;
+    }
+    returns
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+fn sharpe_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = mean(returns);
+    let variance =
+        returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    ratio(mean, variance.sqrt()).map(|sharpe| sharpe * PERIODS_PER_YEAR.sqrt())
+}
+
+fn sortino_ratio(returns: &[f64]) -> Option<f64> {
+    if returns.len() < 2 {
+        return None;
+    }
+    let downside = returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / returns.len() as f64;
+    ratio(mean(returns), downside.sqrt()).map(|sortino| sortino * PERIODS_PER_YEAR.sqrt())
+}
+
+fn format_duration(ms: i64) -> String {
+    let duration = Duration::milliseconds(ms);
+    if duration.num_days() > 0 {
+        format!("{}d {}h", duration.num_days(), duration.num_hours() % 24)
+    } else {
+        format!("{}h {}m", duration.num_hours(), duration.num_minutes() % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn fill(timestamp: i64, closed_pnl: f64) -> CustomUserFills {
+        CustomUserFills {
+            closed_pnl,
+            coin: "BTC".to_string(),
+            crossed: true,
+            dir: "Close Long".to_string(),
+            hash: format!("0x{:x}", timestamp),
+            order_id: timestamp,
+            price: 100.0,
+            side: "A".to_string(),
+            start_position: 1.0,
+            size: 1.0,
+            timestamp,
+            fee: 0.0,
+            tid: timestamp,
+        }
+    }
+
+    fn point(timestamp: i64, equity: f64) -> EquityPoint {
+        EquityPoint { timestamp, equity }
+    }
+
+    #[test]
+    fn report_without_fills() {
+        let report = PnlReport::from_fills(&[], &ReportOptions::default().initial_equity(100.0));
+        assert_eq!(report.total.fills, 0);
+        assert_eq!((report.start_time, report.end_time), (0, 0));
+        assert!(report.by_day.is_empty());
+        assert_eq!(report.max_drawdown, 0.0);
+        assert_eq!(report.max_drawdown_pct, Some(0.0));
+        assert_eq!(report.max_drawdown_duration_ms, 0);
+        assert_eq!(report.sharpe_ratio, None);
+        assert_eq!(report.sortino_ratio, None);
+        assert_eq!(report.trades.win_rate, None);
+    }
+
+    #[test]
+    fn report_of_a_single_day() {
+        let fills = [fill(1_000, 10.0), fill(2_000, -4.0)];
+        let report = PnlReport::from_fills(&fills, &ReportOptions::default().initial_equity(100.0));
+        assert_eq!(report.by_day.len(), 1);
+        assert_eq!(report.total.net_pnl, 6.0);
+        assert_eq!(report.sharpe_ratio, None);
+        assert_eq!(report.trades.win_rate, Some(0.5));
+        assert_eq!(report.trades.profit_factor, Some(2.5));
+    }
+
+    #[test]
+    fn drawdown_recovered() {
+        let curve = [point(1_000, 110.0), point(2_000, 90.0), point(4_000, 120.0)];
+        let (max_drawdown, max_drawdown_pct, duration) = drawdown(&curve, 100.0, 4_000);
+        assert_eq!(max_drawdown, 20.0);
+        assert_eq!(max_drawdown_pct, Some(20.0 / 110.0));
+        assert_eq!(duration, 3_000);
+    }
+
+    #[test]
+    fn drawdown_under_water_at_the_end() {
+        let curve = [point(1_000, 110.0), point(2_000, 90.0), point(3_000, 95.0)];
+        let (max_drawdown, _, duration) = drawdown(&curve, 100.0, 5_000);
+        assert_eq!(max_drawdown, 20.0);
+        assert_eq!(duration, 4_000);
+    }
+
+    #[test]
+    fn drawdown_without_initial_equity() {
+        let curve = [point(1_000, -5.0)];
+        let (max_drawdown, max_drawdown_pct, duration) = drawdown(&curve, 0.0, 1_000);
+        assert_eq!(max_drawdown, 5.0);
+        assert_eq!(max_drawdown_pct, None);
+        assert_eq!(duration, 0);
+    }
+
+    #[test]
+    fn daily_returns_fill_empty_days_with_zero() {
+        let mut by_day = BTreeMap::new();
+        for (day, net_pnl) in [(0, 10.0), (2 * DAY_MS, -5.5)] {
+            by_day.insert(
+                day,
+                PnlBreakdown {
+                    net_pnl,
+                    ..Default::default()
+                },
+            );
+        }
+        assert_eq!(daily_returns(&by_day, 100.0), vec![0.1, 0.0, -0.05]);
+        assert_eq!(daily_returns(&by_day, 0.0), vec![10.0, 0.0, -5.5]);
+        assert!(daily_returns(&BTreeMap::new(), 100.0).is_empty());
+    }
+
+    #[test]
+    fn sharpe_ratio_annualizes_daily_returns() {
+        assert_eq!(sharpe_ratio(&[]), None);
+        assert_eq!(sharpe_ratio(&[0.01]), None);
+        assert_eq!(sharpe_ratio(&[0.01, 0.01]), None);
+        let sharpe = sharpe_ratio(&[0.01, 0.03]).unwrap();
+        assert!((sharpe - 0.02 / 0.0002_f64.sqrt() * 365_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn period_start_with_negative_utc_offset() {
+        let offset = FixedOffset::west_opt(5 * 3600).unwrap();
+        // 03:00 UTC on Jan 2 is still Jan 1 in UTC-5
+        let timestamp = Utc
+            .with_ymd_and_hms(2024, 1, 2, 3, 0, 0)
+            .unwrap()
+            .timestamp_millis();
+        let day_start = offset
+            .with_ymd_and_hms(2024, 1, 1, 0, 0, 0)
+            .unwrap()
+            .timestamp_millis();
+        assert_eq!(period_start(timestamp, offset, DAY_MS), day_start);
+        assert_eq!(
+            period_start(timestamp + 30 * 60 * 1000, offset, HOUR_MS),
+            timestamp
+        );
+    }
+
+    #[test]
+    fn period_start_before_the_epoch() {
+        let utc = FixedOffset::east_opt(0).unwrap();
+        assert_eq!(period_start(-1, utc, DAY_MS), -DAY_MS);
+    }
+}
@@ -0,0 +1,100 @@
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// `[retry]` section of the bot config: timeouts and retries of `HttpClient` calls.
+/// Info queries are retried on any failure, orders only when they carry a cloid.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryConfig {
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub method_timeouts_ms: HashMap<String, u64>, // e.g. fetch_user_fills_by_time = 30000
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32, // Attempts after the first one, 0 disables retries
+    #[serde(default = "default_initial_backoff_ms")]
+    pub initial_backoff_ms: u64,
+    #[serde(default = "default_max_backoff_ms")]
+    pub max_backoff_ms: u64,
+    #[serde(default = "default_true")]
+    pub retry_orders: bool, // Resend orders with a cloid unless the exchange already has them
+}
+
+fn default_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_initial_backoff_ms() -> u64 {
+    200
+}
+
+fn default_max_backoff_ms() -> u64 {
+    5_000
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            timeout_ms: default_timeout_ms(),
+            method_timeouts_ms: HashMap::new(),
+            max_retries: default_max_retries(),
+            initial_backoff_ms: default_initial_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+            retry_orders: default_true(),
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if self.timeout_ms == 0 {
+            errors.push("retry.timeout_ms: must be greater than 0".to_string());
+        }
+        for (method, timeout) in &self.method_timeouts_ms {
+            if *timeout == 0 {
+                errors.push(format!(
+                    "retry.method_timeouts_ms.{}: must be greater than 0",
+                    method
+                ));
+            }
+        }
+        if self.initial_backoff_ms > self.max_backoff_ms {
+            errors.push(
+                "retry.initial_backoff_ms: must not be greater than max_backoff_ms".to_string(),
+            );
+        }
+        errors
+    }
+
+    /// Timeout of one attempt, `method` is the `HttpClient` method name
+    pub fn timeout(&self, method: &str) -> Duration {
+        let timeout_ms = self
+            .method_timeouts_ms
+            .get(method)
+            .copied()
+            .unwrap_or(self.timeout_ms);
+        Duration::from_millis(timeout_ms)
+    }
+
+    /// Exponential backoff before retry `attempt` (0 based), randomized between half and
+    /// the full delay so bots that failed together don't retry together
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let delay_ms = self
+            .initial_backoff_ms
+            .saturating_mul(1 << attempt.min(16))
+            .min(self.max_backoff_ms);
+        let jittered_ms = rand::thread_rng().gen_range(delay_ms / 2..=delay_ms);
+        Duration::from_millis(jittered_ms)
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::VecDeque;
+
+/// Fixed-capacity buffer that drops its oldest items. Every pushed item gets the next
+/// sequence number, so sequences increase from front to back.
+#[derive(Debug, Clone)]
+pub struct RingBuffer<T> {
+    items: VecDeque<(u64, T)>,
+    capacity: usize,
+    last_seq: u64,
+}
+
+impl<T: Clone> RingBuffer<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            items: VecDeque::with_capacity(capacity.min(1024)),
+            capacity,
+            last_seq: 0,
+        }
+    }
+
+    /// Appends an item and returns its sequence
+    pub fn push(&mut self, item: T) -> u64 {
+        self.last_seq += 1;
+        if self.capacity == 0 {
+            return self.last_seq;
+        }
+        if self.items.len() == self.capacity {
+            self.items.pop_front();
+        }
+        self.items.push_back((self.last_seq, item));
+        self.last_seq
+    }
+
+    /// Removes the newest item matching `predicate`, searching from the back
+    pub fn remove_last_matching(&mut self, predicate: impl Fn(&T) -> bool) -> Option<T> {
+        let index = self.items.iter().rposition(|(_, item)| predicate(item))?;
+        self.items.remove(index).map(|(_, item)| item)
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.items.len() > capacity {
+            self.items.pop_front();
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&T> {
+        self.items.back().map(|(_, item)| item)
+    }
+
+    /// Sequence of the last pushed item, 0 before the first push
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        self.items.iter().map(|(_, item)| item)
+    }
+
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().cloned().collect()
+    }
+
+    /// Items with a sequence above `cursor`. Items that already dropped out are skipped.
+    pub fn since(&self, cursor: u64) -> Vec<T> {
+        let start = self.items.partition_point(|(seq, _)| *seq <= cursor);
+        self.items
+            .range(start..)
+            .map(|(_, item)| item.clone())
+            .collect()
+    }
+}
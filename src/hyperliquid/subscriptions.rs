@@ -1,6 +1,7 @@
 use ethers::types::H160;
 use hyperliquid_rust_sdk::Subscription as HyperliquidSubscription;
 
+#[derive(Debug, Clone)]
 pub enum Subscription {
     AllMids,
     Trades { coin: String },
@@ -1,144 +1,270 @@
-use crate::hyperliquid::db::save_fills_to_db;
+use crate::hyperliquid::db::Storage;
+use crate::hyperliquid::events::{EventFilter, EventReceiver, MarketEvent};
+use crate::hyperliquid::fill_writer::{spawn_fill_writer, FillBatch};
+use crate::hyperliquid::http::HttpClient;
 use crate::hyperliquid::model::{CustomCandle, CustomL2Book, CustomTrade, CustomUserFills};
 use crate::hyperliquid::portfolio::{PortfolioManager, Position};
+use crate::hyperliquid::ring_buffer::RingBuffer;
 use crate::hyperliquid::subscriptions::Subscription;
+use crate::utils::metrics::{
+    FEES, FILLS, REALIZED_PNL, UNREALIZED_PNL, WS_BUFFER_SIZE, WS_MESSAGES, WS_RESUBSCRIBES,
+};
 use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
 use ethers::types::H160;
 use hyperliquid_rust_sdk::{BaseUrl, InfoClient, Message, Subscription as HyperliquidSubscription};
-use log::{error, info};
+use log::{error, info, warn};
 use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::io::Write;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::unbounded_channel;
-use tokio::sync::{mpsc::UnboundedSender, RwLock};
-use tokio_postgres::Client;
+use tokio::sync::{broadcast, mpsc::UnboundedSender, RwLock};
 
-#[derive(Clone, Debug)]
+const EVENT_CHANNEL_CAPACITY: usize = 4096;
+const STALE_RESUBSCRIBE_BACKOFF_SECS: u64 = 30;
+
+/// Market data shared by the subscription tasks. Every stream has its own lock so a
+/// burst of trades never blocks book readers, and the latest book is published as a
+/// lock-free snapshot.
+#[derive(Debug)]
 pub struct WsData {
-    pub all_mids: HashMap<String, String>,
-    pub trades: Vec<CustomTrade>,
-    pub max_trades: usize,
-    pub candles: Vec<CustomCandle>,
-    pub max_candles: usize,
-    pub user_fills: Vec<CustomUserFills>,
-    pub max_fills: usize,
-    pub l2_books: Vec<CustomL2Book>,
-    pub max_l2_book: usize,
+    pub all_mids: RwLock<HashMap<String, String>>,
+    pub trades: RwLock<RingBuffer<CustomTrade>>,
+    pub candles: RwLock<RingBuffer<CustomCandle>>,
+    pub user_fills: RwLock<RingBuffer<CustomUserFills>>,
+    pub l2_books: RwLock<RingBuffer<CustomL2Book>>,
+    pub book: ArcSwap<BookSnapshot>,
+    pub portfolio_manager: RwLock<PortfolioManager>,
+    pub account_address: RwLock<Option<H160>>, // Only fills of this account update the portfolio
+    pub health: RwLock<HealthState>,
+    pub events: broadcast::Sender<MarketEvent>,
+    fill_writer: UnboundedSender<FillBatch>,
+}
+
+/// Latest order book with its best prices, replaced as a whole on every update
+#[derive(Debug, Clone, Default)]
+pub struct BookSnapshot {
+    pub l2_book: Option<CustomL2Book>,
     pub best_bid: f64,
     pub best_ask: f64,
-    pub db_client: Option<Arc<Client>>,
-    pub portfolio_manager: PortfolioManager,
 }
 
-impl Default for WsData {
+#[derive(Debug, Default)]
+pub struct HealthState {
+    pub last_message: HashMap<String, Instant>, // Keyed like WebSocketManager::subscription
+    pub disconnected_at: Option<Instant>,
+    pub last_resubscribe: Option<Instant>,
+    pub staleness_thresholds: StalenessThresholds,
+}
+
+/// How long each subscription may stay silent before its data counts as stale.
+/// `None` disables the check, user fills are only sent when the account trades.
+#[derive(Clone, Debug)]
+pub struct StalenessThresholds {
+    pub all_mids: Option<Duration>,
+    pub trades: Option<Duration>,
+    pub candle: Option<Duration>,
+    pub l2_book: Option<Duration>,
+    pub user_fills: Option<Duration>,
+}
+
+impl Default for StalenessThresholds {
     fn default() -> Self {
         Self {
-            all_mids: HashMap::new(),
-            trades: Vec::new(),
-            max_trades: 10000, // Default limit for trades
-            candles: Vec::new(),
-            max_candles: 10000, // Default limit for candles
-            user_fills: Vec::new(),
-            max_fills: 10000,
-            l2_books: Vec::new(),
-            max_l2_book: 100,
-            best_bid: 0.0,
-            best_ask: 0.0,
-            db_client: None,
-            portfolio_manager: PortfolioManager::new(),
+            all_mids: Some(Duration::from_secs(10)),
+            trades: None, // Quiet markets can go minutes without a trade
+            candle: Some(Duration::from_secs(120)),
+            l2_book: Some(Duration::from_secs(10)),
+            user_fills: None,
         }
     }
 }
 
-impl WsData {
-    pub fn add_trade(&mut self, new_trades: Vec<CustomTrade>) {
-        self.trades.extend(new_trades);
-        if self.trades.len() > self.max_trades {
-            let excess = self.trades.len() - self.max_trades;
-            self.trades.drain(0..excess);
+impl StalenessThresholds {
+    pub fn for_subscription(&self, subscription: &Subscription) -> Option<Duration> {
+        match subscription {
+            Subscription::AllMids => self.all_mids,
+            Subscription::Trades { .. } => self.trades,
+            Subscription::Candle { .. } => self.candle,
+            Subscription::L2Book { .. } => self.l2_book,
+            Subscription::UserFills { .. } => self.user_fills,
         }
     }
+}
 
-    pub fn add_candle(&mut self, new_candle: CustomCandle) {
-        if let Some(existing_candle) = self
-            .candles
-            .iter_mut()
-            .find(|candle| candle.time_open == new_candle.time_open)
-        {
-            *existing_candle = new_candle; // Overwrite the existing candle with the same time
-        } else {
-            self.candles.push(new_candle);
-        }
+struct SubscriptionEntry {
+    subscription: Subscription,
+    id: u32,
+    subscribed_at: Instant,
+}
 
-        if self.candles.len() > self.max_candles {
-            let excess = self.candles.len() - self.max_candles;
-            self.candles.drain(0..excess);
+impl WsData {
+    /// Must be called inside a tokio runtime, fills are persisted by a background task
+    pub fn new(storage: Option<Arc<dyn Storage>>) -> Self {
+        Self {
+            all_mids: RwLock::new(HashMap::new()),
+            trades: RwLock::new(RingBuffer::new(10000)), // Default limit for trades
+            candles: RwLock::new(RingBuffer::new(10000)), // Default limit for candles
+            user_fills: RwLock::new(RingBuffer::new(10000)),
+            l2_books: RwLock::new(RingBuffer::new(100)),
+            book: ArcSwap::from_pointee(BookSnapshot::default()),
+            portfolio_manager: RwLock::new(PortfolioManager::new()),
+            account_address: RwLock::new(None),
+            health: RwLock::new(HealthState::default()),
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            fill_writer: spawn_fill_writer(storage),
         }
     }
 
-    pub fn add_l2_book(&mut self, new_l2_book: CustomL2Book) {
-        self.l2_books.push(new_l2_book);
-        if self.l2_books.len() > self.max_l2_book {
-            let excess = self.l2_books.len() - self.max_l2_book;
-            self.l2_books.drain(0..excess);
+    pub async fn add_trade(&self, new_trades: Vec<CustomTrade>) {
+        let mut trades = self.trades.write().await;
+        for trade in new_trades {
+            let seq = trades.push(trade.clone());
+            self.publish(MarketEvent::Trade { seq, trade });
         }
+        WS_BUFFER_SIZE
+            .with_label_values(&["trades"])
+            .set(trades.len() as i64);
+    }
 
-        if let Some(latest_l2_book) = self.l2_books.last() {
-            self.best_bid = latest_l2_book
-                .bid_levels
-                .first()
-                .map_or(0.0, |bid| bid.price);
-            self.best_ask = latest_l2_book
-                .ask_levels
-                .first()
-                .map_or(0.0, |ask| ask.price);
-        }
+    // Appends trades fetched over HTTP that the stream missed. They are not sorted in,
+    // so sequence cursors keep working.
+    async fn merge_trades(&self, coin: &str, fetched: Vec<CustomTrade>) -> usize {
+        let missing: Vec<CustomTrade> = {
+            let trades = self.trades.read().await;
+            let latest = trades
+                .iter()
+                .filter(|trade| trade.coin == coin)
+                .map(|trade| trade.timestamp)
+                .max()
+                .unwrap_or(0);
+            fetched
+                .into_iter()
+                .filter(|trade| trade.timestamp >= latest)
+                .filter(|trade| {
+                    !trades.iter().rev().any(|known| {
+                        known.hash == trade.hash
+                            && known.timestamp == trade.timestamp
+                            && known.price == trade.price
+                            && known.size == trade.size
+                    })
+                })
+                .collect()
+        };
+        let count = missing.len();
+        self.add_trade(missing).await;
+        count
     }
 
-    pub async fn add_fills(&mut self, fills: Vec<CustomUserFills>, user: H160) {
-        self.user_fills.extend(fills.clone());
+    pub async fn add_candle(&self, new_candle: CustomCandle) {
+        let mut candles = self.candles.write().await;
+        // An update of a candle with the same time replaces it and moves it to the end,
+        // so it gets a new sequence. Updates almost always hit the newest candle.
+        candles.remove_last_matching(|candle| {
+            candle.time_open == new_candle.time_open && candle.coin == new_candle.coin
+        });
+        let seq = candles.push(new_candle.clone());
+        self.publish(MarketEvent::Candle {
+            seq,
+            candle: new_candle,
+        });
+        WS_BUFFER_SIZE
+            .with_label_values(&["candles"])
+            .set(candles.len() as i64);
+    }
 
-        if self.user_fills.len() > self.max_fills {
-            let excess = self.user_fills.len() - self.max_fills;
-            self.user_fills.drain(0..excess);
-        }
+    pub async fn add_l2_book(&self, new_l2_book: CustomL2Book) {
+        self.book.store(Arc::new(BookSnapshot {
+            best_bid: new_l2_book.bid_levels.first().map_or(0.0, |bid| bid.price),
+            best_ask: new_l2_book.ask_levels.first().map_or(0.0, |ask| ask.price),
+            l2_book: Some(new_l2_book.clone()),
+        }));
+
+        let mut l2_books = self.l2_books.write().await;
+        let seq = l2_books.push(new_l2_book.clone());
+        self.publish(MarketEvent::L2Book {
+            seq,
+            l2_book: new_l2_book,
+        });
+        WS_BUFFER_SIZE
+            .with_label_values(&["l2_books"])
+            .set(l2_books.len() as i64);
+    }
 
-        for fill in &fills {
-            self.portfolio_manager.update_position(fill);
+    pub async fn add_fills(&self, fills: Vec<CustomUserFills>, user: H160) {
+        {
+            let mut user_fills = self.user_fills.write().await;
+            for fill in &fills {
+                let seq = user_fills.push(fill.clone());
+                self.publish(MarketEvent::UserFill {
+                    seq,
+                    fill: fill.clone(),
+                });
+            }
+            WS_BUFFER_SIZE
+                .with_label_values(&["user_fills"])
+                .set(user_fills.len() as i64);
         }
 
-        if let Some(db_client) = &self.db_client {
-            if let Err(e) = save_fills_to_db(db_client, &fills, user).await {
-                error!("Failed to save fills to database: {}", e);
+        let account_address = *self.account_address.read().await;
+        if account_address.is_none_or(|address| address == user) {
+            let mut portfolio_manager = self.portfolio_manager.write().await;
+            for fill in &fills {
+                portfolio_manager.update_position(fill);
+                FILLS.with_label_values(&[&fill.coin]).inc();
+                FEES.with_label_values(&[&fill.coin]).add(fill.fee);
             }
-        } else if let Err(e) = self.append_fills_to_file(fills, user) {
-            error!("Failed to append fills to file: {}", e);
+            drop(portfolio_manager);
+            self.update_pnl_metrics().await;
+        }
+
+        if self.fill_writer.send(FillBatch { user, fills }).is_err() {
+            error!("Fill writer stopped, fills were not persisted");
         }
     }
 
-    fn append_fills_to_file(&self, fills: Vec<CustomUserFills>, user: H160) -> Result<()> {
-        let file_name = format!("{:?}_fills.log", user);
+    pub async fn set_all_mids(&self, all_mids: HashMap<String, String>) {
+        self.publish(MarketEvent::AllMids(all_mids.clone()));
+        *self.all_mids.write().await = all_mids;
+        self.update_pnl_metrics().await;
+    }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(file_name)
-            .context("Failed to open user fills file")?;
+    fn publish(&self, event: MarketEvent) {
+        // Fails only when nobody is subscribed
+        let _ = self.events.send(event);
+    }
 
-        for fill in fills {
-            let json = serde_json::to_string(&fill).context("Failed to serialize fill")?;
-            writeln!(file, "{}", json).context("Failed to write to user fills file")?;
+    pub async fn update_pnl_metrics(&self) {
+        let all_mids = self.all_mids.read().await;
+        let portfolio_manager = self.portfolio_manager.read().await;
+        for (coin, position) in portfolio_manager.get_positions() {
+            let current_price = all_mids
+                .get(coin)
+                .and_then(|price| price.parse::<f64>().ok());
+            REALIZED_PNL
+                .with_label_values(&[coin])
+                .set(position.pnl.realized);
+            if let Some(current_price) = current_price {
+                UNREALIZED_PNL
+                    .with_label_values(&[coin])
+                    .set(portfolio_manager.get_unrealized_pnl(coin, current_price));
+            }
         }
+    }
 
-        Ok(())
+    pub fn best_bid(&self) -> f64 {
+        self.book.load().best_bid
+    }
+
+    pub fn best_ask(&self) -> f64 {
+        self.book.load().best_ask
     }
 
     pub fn calculate_thickness(&self) -> (f64, f64) {
         let mut bid_thickness = 0.0;
         let mut ask_thickness = 0.0;
 
-        if let Some(latest_book) = self.l2_books.last() {
+        if let Some(latest_book) = &self.book.load().l2_book {
             for bid in &latest_book.bid_levels {
                 bid_thickness += bid.size;
             }
@@ -150,12 +276,13 @@ impl WsData {
         (bid_thickness, ask_thickness)
     }
 
-    pub fn calculate_average_thickness(&self) -> (f64, f64) {
+    pub async fn calculate_average_thickness(&self) -> (f64, f64) {
         let mut total_bid_thickness = 0.0;
         let mut total_ask_thickness = 0.0;
-        let count = self.l2_books.len() as f64;
+        let l2_books = self.l2_books.read().await;
+        let count = l2_books.len() as f64;
 
-        for book in &self.l2_books {
+        for book in l2_books.iter() {
             for bid in &book.bid_levels {
                 total_bid_thickness += bid.size;
             }
@@ -183,7 +310,7 @@ impl WsData {
         let mut bid_thickness = 0.0;
         let mut ask_thickness = 0.0;
 
-        if let Some(latest_book) = self.l2_books.last() {
+        if let Some(latest_book) = &self.book.load().l2_book {
             if let Some(best_bid) = latest_book.bid_levels.first() {
                 let bid_min = best_bid.price - (tick_size * tick_range as f64);
                 let bid_max = best_bid.price;
@@ -213,12 +340,12 @@ impl WsData {
 
 pub struct WebSocketManager {
     info_client: Arc<RwLock<InfoClient>>,
-    ws_data: Arc<RwLock<WsData>>,
-    subscription: Arc<RwLock<HashMap<String, u32>>>,
+    ws_data: Arc<WsData>,
+    subscription: Arc<RwLock<HashMap<String, SubscriptionEntry>>>,
 }
 
 impl WebSocketManager {
-    pub async fn new(is_mainnet: bool, db_client: Option<Arc<Client>>) -> Arc<Self> {
+    pub async fn new(is_mainnet: bool, storage: Option<Arc<dyn Storage>>) -> Arc<Self> {
         let base_url = if is_mainnet {
             BaseUrl::Mainnet
         } else {
@@ -234,78 +361,220 @@ impl WebSocketManager {
         Arc::new(Self {
             info_client,
             subscription: Arc::new(RwLock::new(HashMap::new())),
-            ws_data: Arc::new(RwLock::new(WsData {
-                db_client,
-                ..WsData::default()
-            })),
+            ws_data: Arc::new(WsData::new(storage)),
         })
     }
 
     pub async fn subscribe(&self, subscription: Subscription) -> Result<()> {
-        let internal_subscription: HyperliquidSubscription = subscription.into();
-        let subscription_key = serde_json::to_string(&internal_subscription)
-            .context("Failed to serialize subscription")?;
+        let subscription_key = subscription_key(&subscription)?;
 
         let mut subscriptions = self.subscription.write().await;
         if subscriptions.contains_key(&subscription_key) {
             return Ok(());
         }
 
-        let sender = self.create_subscription_channel().await?;
         let subscription_id = self
-            .info_client
-            .write()
-            .await
-            .subscribe(internal_subscription, sender)
-            .await
-            .context("Failed to subscribe")?;
-
-        subscriptions.insert(subscription_key, subscription_id);
+            .send_subscribe(&subscription, subscription_key.clone())
+            .await?;
+
+        subscriptions.insert(
+            subscription_key,
+            SubscriptionEntry {
+                subscription,
+                id: subscription_id,
+                subscribed_at: Instant::now(),
+            },
+        );
 
         Ok(())
     }
 
     pub async fn unsubscribe(&self, subscription: Subscription) -> Result<()> {
-        let internal_subscription: HyperliquidSubscription = subscription.into();
-        let subscription_key = serde_json::to_string(&internal_subscription)
-            .context("Failed to serialize subscription")?;
+        let subscription_key = subscription_key(&subscription)?;
 
         let mut subscriptions = self.subscription.write().await;
-        if let Some(subscription_id) = subscriptions.remove(&subscription_key) {
+        if let Some(entry) = subscriptions.remove(&subscription_key) {
             self.info_client
                 .write()
                 .await
-                .unsubscribe(subscription_id)
+                .unsubscribe(entry.id)
                 .await
                 .context("Failed to unsubscribe")?;
         }
+        self.ws_data
+            .health
+            .write()
+            .await
+            .last_message
+            .remove(&subscription_key);
         Ok(())
     }
 
-    async fn create_subscription_channel(&self) -> Result<UnboundedSender<Message>> {
+    async fn send_subscribe(&self, subscription: &Subscription, key: String) -> Result<u32> {
+        let sender = self.create_subscription_channel(key).await?;
+        self.info_client
+            .write()
+            .await
+            .subscribe(subscription.clone().into(), sender)
+            .await
+            .context("Failed to subscribe")
+    }
+
+    /// Resubscribes after a disconnect or when a stream went stale, then fills the gap
+    /// with HTTP snapshots. Meant to be called periodically, `run_bot` does so.
+    pub async fn check_health(&self, http_client: &HttpClient) -> Result<()> {
+        let (disconnected, stale) = {
+            let subscriptions = self.subscription.read().await;
+            let health = self.ws_data.health.read().await;
+            let recently_resubscribed = health.last_resubscribe.is_some_and(|at| {
+                at.elapsed() < Duration::from_secs(STALE_RESUBSCRIBE_BACKOFF_SECS)
+            });
+            let stale = if recently_resubscribed {
+                Vec::new()
+            } else {
+                stale_keys(&subscriptions, &health)
+            };
+            (health.disconnected_at.is_some(), stale)
+        };
+        if !disconnected && stale.is_empty() {
+            return Ok(());
+        }
+
+        if disconnected {
+            warn!("Websocket disconnected, resubscribing");
+        } else {
+            warn!("Stale subscriptions {:?}, resubscribing", stale);
+        }
+        self.resubscribe_all().await?;
+        self.fill_gaps(http_client).await
+    }
+
+    /// False from a disconnect until `check_health` has resubscribed
+    pub async fn is_connected(&self) -> bool {
+        self.ws_data.health.read().await.disconnected_at.is_none()
+    }
+
+    /// Subscription keys whose last message is older than their staleness threshold
+    pub async fn stale_subscriptions(&self) -> Vec<String> {
+        let subscriptions = self.subscription.read().await;
+        let health = self.ws_data.health.read().await;
+        stale_keys(&subscriptions, &health)
+    }
+
+    /// True while connected and the order book of `coin` (or, without a book
+    /// subscription, the mids) updated within its staleness threshold
+    pub async fn is_market_data_fresh(&self, coin: &str) -> bool {
+        let subscriptions = self.subscription.read().await;
+        let health = self.ws_data.health.read().await;
+        if health.disconnected_at.is_some() {
+            return false;
+        }
+
+        for subscription in [
+            Subscription::L2Book {
+                coin: coin.to_string(),
+            },
+            Subscription::AllMids,
+        ] {
+            let Ok(key) = subscription_key(&subscription) else {
+                continue;
+            };
+            if subscriptions.contains_key(&key) {
+                return is_fresh(
+                    &health,
+                    &subscription,
+                    health.last_message.get(&key).copied(),
+                );
+            }
+        }
+        false
+    }
+
+    async fn resubscribe_all(&self) -> Result<()> {
+        let mut subscriptions = self.subscription.write().await;
+        for (key, entry) in subscriptions.iter_mut() {
+            if let Err(e) = self.info_client.write().await.unsubscribe(entry.id).await {
+                warn!(
+                    "Failed to unsubscribe {} before resubscribing: {:?}",
+                    key, e
+                );
+            }
+            entry.id = self
+                .send_subscribe(&entry.subscription, key.clone())
+                .await
+                .with_context(|| format!("Failed to resubscribe {}", key))?;
+            entry.subscribed_at = Instant::now();
+            info!("Resubscribed {}", key);
+        }
+
+        let mut health = self.ws_data.health.write().await;
+        health.disconnected_at = None;
+        health.last_resubscribe = Some(Instant::now());
+        WS_RESUBSCRIBES.inc();
+        Ok(())
+    }
+
+    // Replaces the book and adds the trades that were missed while the stream was down
+    async fn fill_gaps(&self, http_client: &HttpClient) -> Result<()> {
+        let subscriptions: Vec<Subscription> = self
+            .subscription
+            .read()
+            .await
+            .values()
+            .map(|entry| entry.subscription.clone())
+            .collect();
+
+        for subscription in subscriptions {
+            match subscription {
+                Subscription::L2Book { coin } => {
+                    let l2_book = http_client.fetch_l2_book(&coin).await?;
+                    self.ws_data.add_l2_book(l2_book).await;
+                    info!("Refreshed {} order book after resubscribe", coin);
+                }
+                Subscription::Trades { coin } => {
+                    let trades = http_client.fetch_trades(&coin).await?;
+                    let added = self.ws_data.merge_trades(&coin, trades).await;
+                    info!("Filled {} missed {} trades after resubscribe", added, coin);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    async fn create_subscription_channel(&self, key: String) -> Result<UnboundedSender<Message>> {
         let (sender, mut receiver) = unbounded_channel();
 
         let ws_data = self.ws_data.clone();
         tokio::spawn(async move {
             while let Some(message) = receiver.recv().await {
+                if !matches!(message, Message::NoData) {
+                    ws_data
+                        .health
+                        .write()
+                        .await
+                        .last_message
+                        .insert(key.clone(), Instant::now());
+                }
                 match message {
                     Message::AllMids(all_mids) => {
-                        let mut data = ws_data.write().await;
-                        data.all_mids = all_mids.data.mids;
+                        WS_MESSAGES.with_label_values(&["allMids"]).inc();
+                        ws_data.set_all_mids(all_mids.data.mids).await;
                     }
                     Message::Trades(trades) => {
+                        WS_MESSAGES.with_label_values(&["trades"]).inc();
                         let custom_trades: Vec<CustomTrade> =
                             trades.data.into_iter().map(CustomTrade::from).collect();
 
-                        let mut data = ws_data.write().await;
-                        data.add_trade(custom_trades);
+                        ws_data.add_trade(custom_trades).await;
                     }
                     Message::Candle(candle) => {
+                        WS_MESSAGES.with_label_values(&["candle"]).inc();
                         let custom_candle: CustomCandle = CustomCandle::from(candle.data);
-                        let mut data = ws_data.write().await;
-                        data.add_candle(custom_candle);
+                        ws_data.add_candle(custom_candle).await;
                     }
                     Message::UserFills(user_fills) => {
+                        WS_MESSAGES.with_label_values(&["userFills"]).inc();
                         if user_fills.data.is_snapshot != Some(true) {
                             let custom_fills: Vec<CustomUserFills> = user_fills
                                 .data
@@ -314,17 +583,18 @@ impl WebSocketManager {
                                 .map(CustomUserFills::from)
                                 .collect();
 
-                            let mut data = ws_data.write().await;
-                            data.add_fills(custom_fills, user_fills.data.user).await;
+                            ws_data.add_fills(custom_fills, user_fills.data.user).await;
                         }
                     }
                     Message::L2Book(l2_book) => {
+                        WS_MESSAGES.with_label_values(&["l2Book"]).inc();
                         let custom_l2_book: CustomL2Book = CustomL2Book::from(l2_book.data);
-                        let mut data = ws_data.write().await;
-                        data.add_l2_book(custom_l2_book);
+                        ws_data.add_l2_book(custom_l2_book).await;
                     }
                     Message::NoData => {
                         error!("Disconnected from websocket");
+                        let mut health = ws_data.health.write().await;
+                        health.disconnected_at.get_or_insert_with(Instant::now);
                     }
                     _ => {
                         info!("Unhandled message: {:#?}", message);
@@ -336,66 +606,121 @@ impl WebSocketManager {
     }
 
     pub async fn set_max_trades(&self, max_trades: usize) {
-        let mut ws_data = self.ws_data.write().await;
-        ws_data.max_trades = max_trades;
+        self.ws_data.trades.write().await.set_capacity(max_trades);
         info!("Updated max trades to {}", max_trades);
     }
 
     pub async fn set_max_candles(&self, max_candles: usize) {
-        let mut ws_data = self.ws_data.write().await;
-        ws_data.max_candles = max_candles;
+        self.ws_data.candles.write().await.set_capacity(max_candles);
         info!("Updated max candles to {}", max_candles);
     }
 
     pub async fn set_max_fills(&self, max_fills: usize) {
-        let mut ws_data = self.ws_data.write().await;
-        ws_data.max_fills = max_fills;
+        self.ws_data
+            .user_fills
+            .write()
+            .await
+            .set_capacity(max_fills);
         info!("Updated max user fills to {}", max_fills);
     }
 
     pub async fn set_max_l2_book(&self, max_l2_book: usize) {
-        let mut ws_data = self.ws_data.write().await;
-        ws_data.max_l2_book = max_l2_book;
+        self.ws_data
+            .l2_books
+            .write()
+            .await
+            .set_capacity(max_l2_book);
         info!("Updated max l2 book to {}", max_l2_book);
     }
 
+    pub async fn set_staleness_thresholds(&self, thresholds: StalenessThresholds) {
+        info!("Updated staleness thresholds to {:?}", thresholds);
+        self.ws_data.health.write().await.staleness_thresholds = thresholds;
+    }
+
+    pub async fn set_account_address(&self, account_address: H160) {
+        *self.ws_data.account_address.write().await = Some(account_address);
+        info!("Tracking portfolio for account {:?}", account_address);
+    }
+
+    /// Streams new items as they arrive instead of cloning whole buffers
+    pub async fn subscribe_events(&self, filter: EventFilter) -> EventReceiver {
+        EventReceiver::new(self.ws_data.events.subscribe(), filter)
+    }
+
+    /// Trades with a sequence above `cursor` and the cursor to pass next time.
+    /// Items that already dropped out of the buffer are skipped.
+    pub async fn get_trades_since(&self, cursor: u64) -> (Vec<CustomTrade>, u64) {
+        let trades = self.ws_data.trades.read().await;
+        (trades.since(cursor), trades.last_seq())
+    }
+
+    pub async fn get_candles_since(&self, cursor: u64) -> (Vec<CustomCandle>, u64) {
+        let candles = self.ws_data.candles.read().await;
+        (candles.since(cursor), candles.last_seq())
+    }
+
+    pub async fn get_user_fills_since(&self, cursor: u64) -> (Vec<CustomUserFills>, u64) {
+        let user_fills = self.ws_data.user_fills.read().await;
+        (user_fills.since(cursor), user_fills.last_seq())
+    }
+
+    pub async fn get_l2_books_since(&self, cursor: u64) -> (Vec<CustomL2Book>, u64) {
+        let l2_books = self.ws_data.l2_books.read().await;
+        (l2_books.since(cursor), l2_books.last_seq())
+    }
+
     pub async fn get_all_mids(&self) -> HashMap<String, String> {
-        self.ws_data.read().await.all_mids.clone()
+        self.ws_data.all_mids.read().await.clone()
     }
 
     pub async fn get_trades(&self) -> Vec<CustomTrade> {
-        self.ws_data.read().await.trades.clone()
+        self.ws_data.trades.read().await.to_vec()
     }
 
     pub async fn get_candles(&self) -> Vec<CustomCandle> {
-        self.ws_data.read().await.candles.clone()
+        self.ws_data.candles.read().await.to_vec()
     }
 
     pub async fn get_user_fills(&self) -> Vec<CustomUserFills> {
-        self.ws_data.read().await.user_fills.clone()
+        self.ws_data.user_fills.read().await.to_vec()
     }
 
     pub async fn get_l2_books(&self) -> Vec<CustomL2Book> {
-        self.ws_data.read().await.l2_books.clone()
+        self.ws_data.l2_books.read().await.to_vec()
+    }
+
+    /// Latest order book without taking any lock
+    pub fn get_book_snapshot(&self) -> Arc<BookSnapshot> {
+        self.ws_data.book.load_full()
     }
 
     pub async fn get_best_bid(&self) -> f64 {
-        self.ws_data.read().await.best_bid
+        self.ws_data.best_bid()
     }
 
     pub async fn get_best_ask(&self) -> f64 {
-        self.ws_data.read().await.best_ask
+        self.ws_data.best_ask()
     }
 
     pub async fn get_position(&self, coin: &str) -> Option<Position> {
         self.ws_data
+            .portfolio_manager
             .read()
             .await
-            .portfolio_manager
             .get_position(coin)
             .cloned()
     }
 
+    pub async fn get_positions(&self) -> HashMap<String, Position> {
+        self.ws_data
+            .portfolio_manager
+            .read()
+            .await
+            .get_positions()
+            .clone()
+    }
+
     pub async fn get_unrealized_pnl(&self, coin: &str) -> f64 {
         let current_price = self
             .get_all_mids()
@@ -403,24 +728,58 @@ impl WebSocketManager {
             .get(coin)
             .map_or(0.0, |price| price.parse::<f64>().unwrap_or(0.0));
         self.ws_data
+            .portfolio_manager
             .read()
             .await
-            .portfolio_manager
             .get_unrealized_pnl(coin, current_price)
     }
 
     pub async fn get_thickness(&self) -> (f64, f64) {
-        let ws_data = self.ws_data.read().await;
-        ws_data.calculate_thickness()
+        self.ws_data.calculate_thickness()
     }
 
     pub async fn get_average_thickness(&self) -> (f64, f64) {
-        let ws_data = self.ws_data.read().await;
-        ws_data.calculate_average_thickness()
+        self.ws_data.calculate_average_thickness().await
     }
 
     pub async fn get_thickness_near_best(&self, tick_size: f64, tick_range: usize) -> (f64, f64) {
-        let ws_data = self.ws_data.read().await;
-        ws_data.calculate_thickness_near_best(tick_size, tick_range)
+        self.ws_data
+            .calculate_thickness_near_best(tick_size, tick_range)
     }
 }
+
+fn subscription_key(subscription: &Subscription) -> Result<String> {
+    let internal_subscription: HyperliquidSubscription = subscription.clone().into();
+    serde_json::to_string(&internal_subscription).context("Failed to serialize subscription")
+}
+
+fn is_fresh(
+    health: &HealthState,
+    subscription: &Subscription,
+    last_message: Option<Instant>,
+) -> bool {
+    let Some(threshold) = health.staleness_thresholds.for_subscription(subscription) else {
+        return true;
+    };
+    last_message.is_some_and(|at| at.elapsed() <= threshold)
+}
+
+// A subscription that never received a message is only stale once the threshold
+// passed since it was sent
+fn stale_keys(
+    subscriptions: &HashMap<String, SubscriptionEntry>,
+    health: &HealthState,
+) -> Vec<String> {
+    subscriptions
+        .iter()
+        .filter(|(key, entry)| {
+            let last_message = health
+                .last_message
+                .get(*key)
+                .copied()
+                .unwrap_or(entry.subscribed_at);
+            !is_fresh(health, &entry.subscription, Some(last_message))
+        })
+        .map(|(key, _)| key.clone())
+        .collect()
+}
@@ -1,5 +1,8 @@
 use lazy_static::lazy_static;
+use log::{error, warn};
+use reqwest::multipart::{Form, Part};
 use reqwest::Client;
+use serde::Serialize;
 use serde_json::json;
 use std::{error::Error, sync::Arc};
 use tokio::sync::Mutex;
@@ -8,6 +11,113 @@ lazy_static! {
     static ref NOTIFIER: Arc<Mutex<Option<DiscordNotifier>>> = Arc::new(Mutex::new(None));
 }
 
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Embed {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<u32>, // 0xRRGGBB
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<EmbedField>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image: Option<EmbedImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>, // ISO 8601
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedField {
+    pub name: String,
+    pub value: String,
+    pub inline: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmbedImage {
+    pub url: String,
+}
+
+impl Embed {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: Some(title.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    pub fn color(mut self, color: u32) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn field(mut self, name: &str, value: &str, inline: bool) -> Self {
+        self.fields.push(EmbedField {
+            name: name.to_string(),
+            value: value.to_string(),
+            inline,
+        });
+        self
+    }
+
+    /// Shows an attached file of the same message, e.g. `attachment_image("pnl_chart.png")`
+    pub fn attachment_image(mut self, filename: &str) -> Self {
+        self.image = Some(EmbedImage {
+            url: format!("attachment://{}", filename),
+        });
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: &str) -> Self {
+        self.timestamp = Some(timestamp.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+/// A webhook message with optional embeds and file attachments
+#[derive(Debug, Clone, Default)]
+pub struct DiscordMessage {
+    pub content: Option<String>,
+    pub embeds: Vec<Embed>,
+    pub attachments: Vec<Attachment>,
+}
+
+impl DiscordMessage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn content(mut self, content: &str) -> Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    pub fn embed(mut self, embed: Embed) -> Self {
+        self.embeds.push(embed);
+        self
+    }
+
+    pub fn attachment(mut self, filename: &str, data: Vec<u8>) -> Self {
+        self.attachments.push(Attachment {
+            filename: filename.to_string(),
+            data,
+        });
+        self
+    }
+}
+
 pub struct DiscordNotifier {
     webhook_url: String,
 }
@@ -26,10 +136,37 @@ impl DiscordNotifier {
     }
 
     pub async fn send_message(&self, content: &str) -> Result<(), Box<dyn Error>> {
+        self.send(&DiscordMessage::new().content(content)).await
+    }
+
+    /// Sends JSON, or multipart/form-data when the message has attachments
+    pub async fn send(&self, message: &DiscordMessage) -> Result<(), Box<dyn Error>> {
         let client = Client::new();
-        let payload = json!({ "content": content });
+        let mut payload = json!({
+            "embeds": message.embeds,
+            "attachments": message
+                .attachments
+                .iter()
+                .enumerate()
+                .map(|(id, attachment)| json!({ "id": id, "filename": attachment.filename }))
+                .collect::<Vec<_>>(),
+        });
+        if let Some(content) = &message.content {
+            payload["content"] = json!(content);
+        }
 
-        let response = client.post(&self.webhook_url).json(&payload).send().await?;
+        let request = if message.attachments.is_empty() {
+            client.post(&self.webhook_url).json(&payload)
+        } else {
+            let mut form = Form::new().text("payload_json", payload.to_string());
+            for (id, attachment) in message.attachments.iter().enumerate() {
+                let part =
+                    Part::bytes(attachment.data.clone()).file_name(attachment.filename.clone());
+                form = form.part(format!("files[{}]", id), part);
+            }
+            client.post(&self.webhook_url).multipart(form)
+        };
+        let response = request.send().await.map_err(|e| e.without_url())?;
 
         if response.status().is_success() {
             Ok(())
@@ -45,12 +182,17 @@ impl DiscordNotifier {
 
 /// Send a Discord notification
 pub async fn notify(content: &str) {
+    notify_message(&DiscordMessage::new().content(content)).await;
+}
+
+/// Send a Discord notification with embeds or attachments
+pub async fn notify_message(message: &DiscordMessage) {
     let global_notifier = NOTIFIER.lock().await;
     if let Some(notifier) = &*global_notifier {
-        if let Err(e) = notifier.send_message(content).await {
-            eprintln!("Failed to send Discord notification: {}", e);
+        if let Err(e) = notifier.send(message).await {
+            error!("Failed to send Discord notification: {}", e);
         }
     } else {
-        eprintln!("DiscordNotifier is not initialized.");
+        warn!("DiscordNotifier is not initialized, use [[notifiers]] and notifier::alert instead");
     }
 }
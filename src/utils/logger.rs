@@ -1,59 +1,316 @@
 use anyhow::Result;
-use log::LevelFilter;
+use chrono::{Local, SecondsFormat};
+use lazy_static::lazy_static;
+use log::kv::{self, Key, VisitSource, VisitValue};
+use log::{LevelFilter, Record};
 use log4rs::append::console::ConsoleAppender;
 use log4rs::append::rolling_file::policy::compound::roll::fixed_window::FixedWindowRoller;
 use log4rs::append::rolling_file::policy::compound::trigger::size::SizeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::time::TimeTrigger;
+use log4rs::append::rolling_file::policy::compound::trigger::Trigger;
 use log4rs::append::rolling_file::policy::compound::CompoundPolicy;
 use log4rs::append::rolling_file::RollingFileAppender;
-use log4rs::config::{Appender, Config, Root};
+use log4rs::config::{Appender, Config, Logger, Root};
 use log4rs::encode::pattern::PatternEncoder;
+use log4rs::encode::Encode;
+use log4rs::Handle;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
 use std::env;
+use std::error::Error;
+use std::sync::Mutex;
 
-pub fn setup_logging(log_file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let log_level = match env::var("LOG_LEVEL")
-        .unwrap_or_else(|_| "info".to_string())
-        .to_lowercase()
-        .as_str()
-    {
-        "debug" => LevelFilter::Debug,
-        "trace" => LevelFilter::Trace,
-        "warn" => LevelFilter::Warn,
-        "error" => LevelFilter::Error,
-        _ => LevelFilter::Info,
-    };
+/// Target of the order audit log, e.g. `info!(target: AUDIT_TARGET, cloid = ...; "...")`
+pub const AUDIT_TARGET: &str = "audit";
 
-    let size_limit = 5 * 1024 * 1024;
-    let pattern = format!("{}.{{}}.gz", log_file_path);
+const TEXT_PATTERN: &str = "[{d(%Y-%m-%dT%H:%M:%S.%3f)} {h({l:5.5})}][{M:10.10}:{line:3.3}] {m}{n}";
 
-    let policy = CompoundPolicy::new(
-        Box::new(SizeTrigger::new(size_limit)),
-        Box::new(FixedWindowRoller::builder().build(&pattern, 3)?),
-    );
+lazy_static! {
+    static ref HANDLE: Mutex<Option<Handle>> = Mutex::new(None);
+}
 
-    // ログファイル用のAppender
-    let logfile = RollingFileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "[{d(%Y-%m-%dT%H:%M:%S.%3f)} {h({l:5.5})}][{M:10.10}:{line:3.3}] {m}{n}",
-        )))
-        .build(log_file_path, Box::new(policy))?;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Size,
+    Hourly,
+    Daily,
+}
+
+/// `[logging]` section of the bot config
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingConfig {
+    #[serde(default = "default_level")]
+    pub level: String, // LOG_LEVEL overrides it
+    #[serde(default)]
+    pub modules: HashMap<String, String>, // e.g. "rust_trading::hyperliquid::websocket" = "debug"
+    #[serde(default)]
+    pub format: LogFormat,
+    pub file: Option<String>,
+    #[serde(default)]
+    pub rotation: LogRotation,
+    #[serde(default = "default_max_size_mb")]
+    pub max_size_mb: u64, // Only for size rotation
+    #[serde(default = "default_keep_files")]
+    pub keep_files: u32,
+    pub audit_file: Option<String>, // Order requests and exchange responses, always JSON
+    pub bot_name: Option<String>,   // Defaults to the config file name
+}
+
+fn default_level() -> String {
+    "info".to_string()
+}
+
+fn default_max_size_mb() -> u64 {
+    5
+}
+
+fn default_keep_files() -> u32 {
+    3
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: default_level(),
+            modules: HashMap::new(),
+            format: LogFormat::default(),
+            file: None,
+            rotation: LogRotation::default(),
+            max_size_mb: default_max_size_mb(),
+            keep_files: default_keep_files(),
+            audit_file: None,
+            bot_name: None,
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        if parse_level(&self.level).is_none() {
+            errors.push(format!("logging.level: unknown level {:?}", self.level));
+        }
+        for (module, level) in &self.modules {
+            if parse_level(level).is_none() {
+                errors.push(format!(
+                    "logging.modules.{}: unknown level {:?}",
+                    module, level
+                ));
+            }
+        }
+        if self.max_size_mb == 0 {
+            errors.push("logging.max_size_mb: must be greater than 0".to_string());
+        }
+        if self.keep_files == 0 {
+            errors.push("logging.keep_files: must be greater than 0".to_string());
+        }
+        errors
+    }
+}
+
+/// Fields added to every JSON log line
+#[derive(Debug, Clone, Default)]
+pub struct LogContext {
+    pub bot: Option<String>,
+    pub coin: Option<String>,
+}
+
+pub fn setup_logging(log_file_path: &str) -> Result<(), Box<dyn Error>> {
+    let config = LoggingConfig {
+        file: Some(log_file_path.to_string()),
+        ..Default::default()
+    };
+    init_logging(&config, &LogContext::default())
+}
+
+/// Installs the logger, or swaps in the new config if it is already installed
+pub fn init_logging(config: &LoggingConfig, context: &LogContext) -> Result<(), Box<dyn Error>> {
+    let log_level = env::var("LOG_LEVEL")
+        .ok()
+        .and_then(|level| parse_level(&level))
+        .or_else(|| parse_level(&config.level))
+        .unwrap_or(LevelFilter::Info);
 
     // 標準出力用のAppender
     let stdout = ConsoleAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "[{d(%Y-%m-%dT%H:%M:%S.%3f)} {h({l:5.5})}][{M:10.10}:{line:3.3}] {m}{n}",
-        )))
+        .encoder(encoder(config.format, context))
         .build();
+    let mut builder =
+        Config::builder().appender(Appender::builder().build("stdout", Box::new(stdout)));
+    let mut root = Root::builder().appender("stdout");
+
+    // ログファイル用のAppender
+    if let Some(file) = &config.file {
+        let logfile = rolling_file(file, config, encoder(config.format, context))?;
+        builder = builder.appender(Appender::builder().build("logfile", Box::new(logfile)));
+        root = root.appender("logfile");
+    }
+
+    if let Some(audit_file) = &config.audit_file {
+        let audit = rolling_file(audit_file, config, Box::new(JsonEncoder::new(context)))?;
+        builder = builder
+            .appender(Appender::builder().build("audit", Box::new(audit)))
+            .logger(
+                Logger::builder()
+                    .appender("audit")
+                    .additive(false)
+                    .build(AUDIT_TARGET, LevelFilter::Info),
+            );
+    }
+
+    for (module, level) in &config.modules {
+        let level = parse_level(level).unwrap_or(log_level);
+        builder = builder.logger(Logger::builder().build(module, level));
+    }
 
-    let config = Config::builder()
-        .appender(Appender::builder().build("stdout", Box::new(stdout)))
-        .appender(Appender::builder().build("logfile", Box::new(logfile)))
-        .build(
-            Root::builder()
-                .appender("stdout")
-                .appender("logfile")
-                .build(log_level),
-        )?;
-
-    log4rs::init_config(config)?;
+    let log_config = builder.build(root.build(log_level))?;
+    let mut handle = HANDLE.lock().unwrap_or_else(|e| e.into_inner());
+    match handle.as_ref() {
+        Some(handle) => handle.set_config(log_config),
+        None => *handle = Some(log4rs::init_config(log_config)?),
+    }
     Ok(())
 }
+
+fn parse_level(level: &str) -> Option<LevelFilter> {
+    match level.to_lowercase().as_str() {
+        "off" => Some(LevelFilter::Off),
+        "error" => Some(LevelFilter::Error),
+        "warn" => Some(LevelFilter::Warn),
+        "info" => Some(LevelFilter::Info),
+        "debug" => Some(LevelFilter::Debug),
+        "trace" => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+fn encoder(format: LogFormat, context: &LogContext) -> Box<dyn Encode> {
+    match format {
+        LogFormat::Text => Box::new(PatternEncoder::new(TEXT_PATTERN)),
+        LogFormat::Json => Box::new(JsonEncoder::new(context)),
+    }
+}
+
+fn rolling_file(
+    path: &str,
+    config: &LoggingConfig,
+    encoder: Box<dyn Encode>,
+) -> Result<RollingFileAppender, Box<dyn Error>> {
+    let trigger: Box<dyn Trigger> = match config.rotation {
+        LogRotation::Size => Box::new(SizeTrigger::new(config.max_size_mb * 1024 * 1024)),
+        LogRotation::Hourly => Box::new(time_trigger("1 hour")?),
+        LogRotation::Daily => Box::new(time_trigger("1 day")?),
+    };
+    let pattern = format!("{}.{{}}.gz", path);
+    let roller = FixedWindowRoller::builder().build(&pattern, config.keep_files)?;
+    let policy = CompoundPolicy::new(trigger, Box::new(roller));
+    Ok(RollingFileAppender::builder()
+        .encoder(encoder)
+        .build(path, Box::new(policy))?)
+}
+
+// TimeTriggerConfig can only be deserialized. `modulate` rolls on the hour / at midnight.
+fn time_trigger(interval: &str) -> Result<TimeTrigger, serde_json::Error> {
+    let config = serde_json::from_value(json!({ "interval": interval, "modulate": true }))?;
+    Ok(TimeTrigger::new(config))
+}
+
+/// One JSON object per line with the context fields and the record's key-values,
+/// e.g. `info!(oid = oid, cloid = cloid.as_str(); "Order placed")`
+#[derive(Debug)]
+struct JsonEncoder {
+    context: Map<String, Value>,
+}
+
+impl JsonEncoder {
+    fn new(context: &LogContext) -> Self {
+        let mut fields = Map::new();
+        if let Some(bot) = &context.bot {
+            fields.insert("bot".to_string(), json!(bot));
+        }
+        if let Some(coin) = &context.coin {
+            fields.insert("coin".to_string(), json!(coin));
+        }
+        Self { context: fields }
+    }
+}
+
+impl Encode for JsonEncoder {
+    fn encode(&self, w: &mut dyn log4rs::encode::Write, record: &Record) -> Result<()> {
+        let mut line = Map::new();
+        line.insert(
+            "time".to_string(),
+            json!(Local::now().to_rfc3339_opts(SecondsFormat::Millis, false)),
+        );
+        line.insert("level".to_string(), json!(record.level().as_str()));
+        line.insert("target".to_string(), json!(record.target()));
+        if let Some(line_number) = record.line() {
+            line.insert("line".to_string(), json!(line_number));
+        }
+        line.insert("message".to_string(), json!(record.args().to_string()));
+        line.extend(self.context.clone());
+        record.key_values().visit(&mut FieldVisitor(&mut line))?;
+
+        serde_json::to_writer(&mut *w, &line)?;
+        w.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for FieldVisitor<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let mut field = FieldValue(Value::Null);
+        value.visit(&mut field)?;
+        self.0.insert(key.to_string(), field.0);
+        Ok(())
+    }
+}
+
+// Keeps numbers and booleans typed, `None` becomes null
+struct FieldValue(Value);
+
+impl<'v> VisitValue<'v> for FieldValue {
+    fn visit_any(&mut self, value: kv::Value) -> Result<(), kv::Error> {
+        self.0 = json!(value.to_string());
+        Ok(())
+    }
+
+    fn visit_null(&mut self) -> Result<(), kv::Error> {
+        self.0 = Value::Null;
+        Ok(())
+    }
+
+    fn visit_u64(&mut self, value: u64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_i64(&mut self, value: i64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_f64(&mut self, value: f64) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+
+    fn visit_bool(&mut self, value: bool) -> Result<(), kv::Error> {
+        self.0 = json!(value);
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{Context, Result};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use prometheus::{
+    register_gauge, register_gauge_vec, register_histogram, register_histogram_vec,
+    register_int_counter, register_int_counter_vec, register_int_gauge_vec, Encoder, Gauge,
+    GaugeVec, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use serde::Deserialize;
+use std::net::SocketAddr;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+lazy_static! {
+    pub static ref ORDERS_SENT: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_orders_sent_total",
+        "Order and cancel requests sent to the exchange",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref ORDERS_REJECTED: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_orders_rejected_total",
+        "Order and cancel requests that failed or were rejected",
+        &["kind"]
+    )
+    .unwrap();
+    pub static ref FILLS: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_fills_total",
+        "Fills received for the tracked account",
+        &["coin"]
+    )
+    .unwrap();
+    // A gauge because maker rebates make fees negative
+    pub static ref FEES: GaugeVec = register_gauge_vec!(
+        "hyperliquid_fees",
+        "Accumulated fees of the tracked account",
+        &["coin"]
+    )
+    .unwrap();
+    pub static ref REALIZED_PNL: GaugeVec = register_gauge_vec!(
+        "hyperliquid_realized_pnl",
+        "Realized PnL per coin from the portfolio manager",
+        &["coin"]
+    )
+    .unwrap();
+    pub static ref UNREALIZED_PNL: GaugeVec = register_gauge_vec!(
+        "hyperliquid_unrealized_pnl",
+        "Unrealized PnL per coin at the latest mid price",
+        &["coin"]
+    )
+    .unwrap();
+    pub static ref WS_MESSAGES: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_ws_messages_total",
+        "Websocket messages received per subscription channel",
+        &["channel"]
+    )
+    .unwrap();
+    pub static ref WS_RESUBSCRIBES: IntCounter = register_int_counter!(
+        "hyperliquid_ws_resubscribes_total",
+        "Resubscriptions after a disconnect or stale stream"
+    )
+    .unwrap();
+    pub static ref WS_BUFFER_SIZE: IntGaugeVec = register_int_gauge_vec!(
+        "hyperliquid_ws_buffer_size",
+        "Number of items held in the WsData buffers",
+        &["buffer"]
+    )
+    .unwrap();
+    pub static ref EXECUTE_DURATION: Histogram = register_histogram!(
+        "bot_execute_duration_seconds",
+        "Duration of BotFramework::execute"
+    )
+    .unwrap();
+    pub static ref EXECUTE_ERRORS: IntCounter = register_int_counter!(
+        "bot_execute_errors_total",
+        "BotFramework::execute calls that returned an error"
+    )
+    .unwrap();
+    pub static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "hyperliquid_http_request_duration_seconds",
+        "Latency of single HttpClient request attempts",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref HTTP_RETRIES: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_http_retries_total",
+        "HttpClient requests sent again after a failure or timeout",
+        &["method"]
+    )
+    .unwrap();
+    pub static ref RATE_LIMIT_REMAINING: Gauge = register_gauge!(
+        "hyperliquid_rate_limit_remaining_weight",
+        "Request weight left in the HttpClient rate limit budget"
+    )
+    .unwrap();
+    pub static ref RATE_LIMITED: IntCounterVec = register_int_counter_vec!(
+        "hyperliquid_rate_limited_total",
+        "Requests rejected because the rate limit budget was exhausted",
+        &["priority"]
+    )
+    .unwrap();
+}
+
+/// `[metrics]` section of the bot config
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsConfig {
+    pub listen: SocketAddr, // e.g. "127.0.0.1:9184"
+}
+
+pub fn record_order(kind: &str, success: bool) {
+    ORDERS_SENT.with_label_values(&[kind]).inc();
+    if !success {
+        ORDERS_REJECTED.with_label_values(&[kind]).inc();
+    }
+}
+
+/// Renders all registered metrics in the Prometheus text format
+pub fn gather() -> Result<String> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .context("Failed to encode metrics")?;
+    String::from_utf8(buffer).context("Metrics are not valid UTF-8")
+}
+
+/// Serves `GET /metrics` on the given address
+pub async fn spawn_metrics_server(config: &MetricsConfig) -> Result<()> {
+    let listener = TcpListener::bind(config.listen)
+        .await
+        .with_context(|| format!("Failed to bind metrics server to {}", config.listen))?;
+    info!(
+        "Metrics server listening on http://{}/metrics",
+        config.listen
+    );
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    error!("Failed to accept metrics connection: {}", e);
+                    continue;
+                }
+            };
+            tokio::spawn(async move {
+                let mut stream = BufReader::new(stream);
+                let mut request_line = String::new();
+                if stream.read_line(&mut request_line).await.is_err() {
+                    return;
+                }
+                // Skip the headers, scrapes have no body
+                loop {
+                    let mut header = String::new();
+                    match stream.read_line(&mut header).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(_) if header.trim().is_empty() => break,
+                        Ok(_) => {}
+                    }
+                }
+
+                let (status, body) = if request_line.starts_with("GET /metrics") {
+                    match gather() {
+                        Ok(body) => ("200 OK", body),
+                        Err(e) => ("500 Internal Server Error", e.to_string()),
+                    }
+                } else {
+                    ("404 Not Found", "Not Found".to_string())
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let stream = stream.get_mut();
+                if let Err(e) = stream.write_all(response.as_bytes()).await {
+                    warn!("Failed to write metrics response: {}", e);
+                }
+                let _ = stream.shutdown().await;
+            });
+        }
+    });
+    Ok(())
+}
@@ -1,3 +1,5 @@
 pub mod time;
 pub mod discord;
-pub mod logger;
\ No newline at end of file
+pub mod logger;
+pub mod metrics;
+pub mod notifier;
\ No newline at end of file
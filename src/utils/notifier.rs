@@ -0,0 +1,468 @@
+use crate::utils::discord::{DiscordMessage, DiscordNotifier, Embed};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use chrono::Utc;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+// Discord caps embed descriptions at 4096 and Slack/Telegram messages at 4000 characters
+const MAX_MESSAGE_CHARS: usize = 3500;
+
+lazy_static! {
+    static ref HUB: RwLock<Option<Arc<NotificationHub>>> = RwLock::new(None);
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl Severity {
+    fn label(self) -> &'static str {
+        match self {
+            Severity::Info => "INFO",
+            Severity::Warning => "WARNING",
+            Severity::Error => "ERROR",
+            Severity::Critical => "CRITICAL",
+        }
+    }
+
+    fn color(self) -> u32 {
+        match self {
+            Severity::Info => 0x2196f3,
+            Severity::Warning => 0xffb300,
+            Severity::Error => 0xe53935,
+            Severity::Critical => 0x8e24aa,
+        }
+    }
+}
+
+/// A notification. `category` (e.g. "fill", "error", "ws") is what routing rules match on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub severity: Severity,
+    pub category: String,
+    pub title: String,
+    pub message: String,
+    pub timestamp: i64,
+}
+
+impl Alert {
+    pub fn new(severity: Severity, category: &str, title: &str) -> Self {
+        Self {
+            severity,
+            category: category.to_string(),
+            title: title.to_string(),
+            message: String::new(),
+            timestamp: Utc::now().timestamp_millis(),
+        }
+    }
+
+    pub fn message(mut self, message: &str) -> Self {
+        self.message = truncate(message);
+        self
+    }
+
+    fn text(&self) -> String {
+        if self.message.is_empty() {
+            format!("[{}] {}", self.severity.label(), self.title)
+        } else {
+            format!(
+                "[{}] {}\n{}",
+                self.severity.label(),
+                self.title,
+                self.message
+            )
+        }
+    }
+}
+
+fn truncate(message: &str) -> String {
+    match message.char_indices().nth(MAX_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}…", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+#[async_trait]
+impl Notifier for DiscordNotifier {
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let mut embed = Embed::new(&alert.title)
+            .color(alert.severity.color())
+            .field("Severity", alert.severity.label(), true)
+            .field("Category", &alert.category, true);
+        if !alert.message.is_empty() {
+            embed = embed.description(&alert.message);
+        }
+        DiscordNotifier::send(self, &DiscordMessage::new().embed(embed))
+            .await
+            .map_err(|e| anyhow!("{}", e))
+    }
+}
+
+pub struct SlackNotifier {
+    webhook_url: String,
+    client: Client,
+}
+
+impl SlackNotifier {
+    pub fn new(webhook_url: &str) -> Self {
+        Self {
+            webhook_url: webhook_url.to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for SlackNotifier {
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let payload = json!({ "text": alert.text() });
+        let response = self
+            .client
+            .post(&self.webhook_url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url())?;
+        check_status("Slack", response.status())
+    }
+}
+
+pub struct TelegramNotifier {
+    bot_token: String,
+    chat_id: String,
+    client: Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(bot_token: &str, chat_id: &str) -> Self {
+        Self {
+            bot_token: bot_token.to_string(),
+            chat_id: chat_id.to_string(),
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for TelegramNotifier {
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let url = format!("https://api.telegram.org/bot{}/sendMessage", self.bot_token);
+        let payload = json!({ "chat_id": self.chat_id, "text": alert.text() });
+        // The URL carries the bot token, reqwest errors would include it
+        let response = self
+            .client
+            .post(&url)
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| e.without_url())?;
+        check_status("Telegram", response.status())
+    }
+}
+
+/// Posts the alert as JSON: `{"severity", "category", "title", "message", "timestamp"}`
+pub struct WebhookNotifier {
+    url: String,
+    headers: HashMap<String, String>,
+    client: Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str, headers: HashMap<String, String>) -> Self {
+        Self {
+            url: url.to_string(),
+            headers,
+            client: Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let mut request = self.client.post(&self.url).json(alert);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(|e| e.without_url())?;
+        check_status("Webhook", response.status())
+    }
+}
+
+fn check_status(sink: &str, status: reqwest::StatusCode) -> Result<()> {
+    if !status.is_success() {
+        bail!("{} returned status {}", sink, status);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SinkConfig {
+    Discord {
+        webhook_url: String,
+    },
+    Slack {
+        webhook_url: String,
+    },
+    Telegram {
+        bot_token: String,
+        chat_id: String,
+    },
+    Webhook {
+        url: String,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+    },
+}
+
+/// One `[[notifiers]]` entry of the bot config: where alerts go and which ones.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifierConfig {
+    pub sink: SinkConfig,
+    #[serde(default)]
+    pub min_severity: Severity,
+    #[serde(default)]
+    pub categories: Vec<String>, // Empty accepts every category
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: u32,
+    #[serde(default = "default_dedupe_secs")]
+    pub dedupe_secs: u64, // Identical alerts within this window are sent once
+}
+
+fn default_max_per_minute() -> u32 {
+    20
+}
+
+fn default_dedupe_secs() -> u64 {
+    300
+}
+
+impl NotifierConfig {
+    pub fn validate(&self, index: usize) -> Vec<String> {
+        let mut errors = Vec::new();
+        let url = match &self.sink {
+            SinkConfig::Discord { webhook_url } | SinkConfig::Slack { webhook_url } => {
+                Some(webhook_url)
+            }
+            SinkConfig::Webhook { url, .. } => Some(url),
+            SinkConfig::Telegram { bot_token, chat_id } => {
+                if bot_token.is_empty() || chat_id.is_empty() {
+                    errors.push(format!(
+                        "notifiers[{}].sink: bot_token and chat_id must not be empty",
+                        index
+                    ));
+                }
+                None
+            }
+        };
+        if url.is_some_and(|url| !url.starts_with("https://") && !url.starts_with("http://")) {
+            errors.push(format!(
+                "notifiers[{}].sink: url must start with https:// or http://",
+                index
+            ));
+        }
+        if self.max_per_minute == 0 {
+            errors.push(format!(
+                "notifiers[{}].max_per_minute: must be greater than 0",
+                index
+            ));
+        }
+        errors
+    }
+
+    fn build_notifier(&self) -> Arc<dyn Notifier> {
+        match &self.sink {
+            SinkConfig::Discord { webhook_url } => Arc::new(DiscordNotifier::new(webhook_url)),
+            SinkConfig::Slack { webhook_url } => Arc::new(SlackNotifier::new(webhook_url)),
+            SinkConfig::Telegram { bot_token, chat_id } => {
+                Arc::new(TelegramNotifier::new(bot_token, chat_id))
+            }
+            SinkConfig::Webhook { url, headers } => {
+                Arc::new(WebhookNotifier::new(url, headers.clone()))
+            }
+        }
+    }
+}
+
+struct Sink {
+    config: NotifierConfig,
+    notifier: Arc<dyn Notifier>,
+    limiter: Mutex<Limiter>,
+}
+
+#[derive(Default)]
+struct Limiter {
+    sent: VecDeque<Instant>,
+    // When each distinct alert was last sent, and how many copies were held back since
+    recent: HashMap<(String, String, String), (Instant, usize)>,
+    dropped: usize,
+}
+
+impl Sink {
+    fn accepts(&self, alert: &Alert) -> bool {
+        alert.severity >= self.config.min_severity
+            && (self.config.categories.is_empty()
+                || self.config.categories.contains(&alert.category))
+    }
+
+    // Returns the alert to send, noting what was held back since, or None to skip it
+    fn admit(&self, alert: &Alert) -> Option<Alert> {
+        let mut limiter = self.limiter.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        let dedupe_window = Duration::from_secs(self.config.dedupe_secs);
+        // Expired entries with held back copies stay a little longer to report the count
+        limiter.recent.retain(|_, (sent_at, suppressed)| {
+            let age = now.duration_since(*sent_at);
+            age < dedupe_window || (*suppressed > 0 && age < dedupe_window * 2)
+        });
+
+        let key = (
+            alert.category.clone(),
+            alert.title.clone(),
+            alert.message.clone(),
+        );
+        let repeated = match limiter.recent.get_mut(&key) {
+            Some((sent_at, suppressed)) if now.duration_since(*sent_at) < dedupe_window => {
+                *suppressed += 1;
+                return None;
+            }
+            Some((_, suppressed)) => *suppressed,
+            None => 0,
+        };
+
+        while limiter
+            .sent
+            .front()
+            .is_some_and(|sent_at| now.duration_since(*sent_at) >= Duration::from_secs(60))
+        {
+            limiter.sent.pop_front();
+        }
+        if limiter.sent.len() >= self.config.max_per_minute as usize {
+            limiter.dropped += 1;
+            return None;
+        }
+
+        limiter.sent.push_back(now);
+        limiter.recent.insert(key, (now, 0));
+        let dropped = std::mem::take(&mut limiter.dropped);
+        let mut alert = alert.clone();
+        if repeated > 0 {
+            alert.message = format!(
+                "{}\n(repeated {} more times since the last one)",
+                alert.message, repeated
+            );
+        }
+        if dropped > 0 {
+            alert.message = format!(
+                "{}\n({} alerts dropped by the rate limit)",
+                alert.message, dropped
+            );
+        }
+        Some(alert)
+    }
+}
+
+/// Routes alerts to every configured sink that accepts them
+pub struct NotificationHub {
+    sinks: Vec<Sink>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl NotificationHub {
+    pub fn new(configs: &[NotifierConfig]) -> Self {
+        let sinks = configs
+            .iter()
+            .map(|config| Sink {
+                config: config.clone(),
+                notifier: config.build_notifier(),
+                limiter: Mutex::new(Limiter::default()),
+            })
+            .collect();
+        Self {
+            sinks,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Sends in the background so a slow sink does not hold up the caller.
+    /// Returns false when no sink is routed the alert.
+    pub fn send(&self, alert: &Alert) -> bool {
+        let mut routed = false;
+        for sink in self.sinks.iter().filter(|sink| sink.accepts(alert)) {
+            routed = true;
+            if let Some(alert) = sink.admit(alert) {
+                let notifier = sink.notifier.clone();
+                let in_flight = self.in_flight.clone();
+                in_flight.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    if let Err(e) = notifier.send(&alert).await {
+                        error!("Failed to send alert {:?}: {:#}", alert.title, e);
+                    }
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        }
+        routed
+    }
+
+    /// Waits up to `timeout` for the alerts that are still being sent
+    pub async fn flush(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while self.in_flight.load(Ordering::SeqCst) > 0 && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+/// Replaces the global sinks, e.g. after a config reload
+pub async fn init_notifiers(configs: &[NotifierConfig]) {
+    *HUB.write().await = Some(Arc::new(NotificationHub::new(configs)));
+    if !configs.is_empty() {
+        info!("Alerts go to {} notifier(s)", configs.len());
+    }
+}
+
+/// Sends an alert to the global sinks. Alerts that no sink takes are logged instead.
+pub async fn alert(alert: Alert) {
+    let hub = HUB.read().await.clone();
+    if hub.is_some_and(|hub| hub.send(&alert)) {
+        return;
+    }
+    match alert.severity {
+        Severity::Info => info!("{}", alert.text()),
+        Severity::Warning => warn!("{}", alert.text()),
+        Severity::Error | Severity::Critical => error!("{}", alert.text()),
+    }
+}
+
+/// Gives pending alerts a chance to go out, e.g. before the process exits
+pub async fn flush_alerts(timeout: Duration) {
+    let hub = HUB.read().await.clone();
+    if let Some(hub) = hub {
+        hub.flush(timeout).await;
+    }
+}